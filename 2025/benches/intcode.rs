@@ -0,0 +1,195 @@
+use mbsaoc2025::intcode::assembler::assemble;
+use mbsaoc2025::intcode::compiler::CompiledProgram;
+use mbsaoc2025::intcode::intcodecpu::{IntCodeCPU, Modes};
+use mbsaoc2025::intcode::parse_program;
+use std::fs;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/* Compares the decoding execution core against the original interpreter, which decoded every
+ * instruction from scratch (with a Vec of modes), and the compiled closures against the decoding core.
+ * Both sides of each comparison get the same parsed program, so it's only the execution core that's
+ * being measured.  Run with `cargo bench --bench intcode` */
+
+const RUNS: usize = 10;
+
+/* The way IntCodeCPU used to run, kept here as the baseline now the CPU itself has moved on.  It only
+ * has to cope with the programs below, so anything unexpected just panics */
+fn original_modes(opcode: i64, count: usize) -> Vec<Modes> {
+    let mut flags = opcode / 100;
+    (0..count)
+        .map(|_| {
+            let mode = Modes::from(flags % 10).expect("Bad mode");
+            flags /= 10;
+            mode
+        })
+        .collect()
+}
+
+fn original_parameter(cpu: &IntCodeCPU, offset: usize, mode: Modes) -> i64 {
+    let value = cpu.memory.get(cpu.pc + offset);
+    match mode {
+        Modes::Position => cpu.memory.get(value as usize),
+        Modes::Immediate => value,
+        Modes::Relative => cpu.memory.get((cpu.relative_base + value) as usize),
+    }
+}
+
+fn original_address(cpu: &IntCodeCPU, offset: usize, mode: Modes) -> usize {
+    let value = cpu.memory.get(cpu.pc + offset);
+    match mode {
+        Modes::Position => value as usize,
+        Modes::Immediate => panic!("Write target in immediate mode"),
+        Modes::Relative => (cpu.relative_base + value) as usize,
+    }
+}
+
+fn run_original(cpu: &mut IntCodeCPU) {
+    while cpu.running {
+        let opcode = cpu.memory.get(cpu.pc);
+        let size = match opcode % 100 {
+            1 | 2 | 7 | 8 => 4,
+            5 | 6 => 3,
+            3 | 4 | 9 => 2,
+            99 => 1,
+            _ => panic!("Bad opcode {}", opcode),
+        };
+        let modes = original_modes(opcode, size - 1);
+        match opcode % 100 {
+            1 | 2 | 7 | 8 => {
+                let target = original_address(cpu, 3, modes[2]);
+                let a = original_parameter(cpu, 1, modes[0]);
+                let b = original_parameter(cpu, 2, modes[1]);
+                let value = match opcode % 100 {
+                    1 => a + b,
+                    2 => a * b,
+                    7 => (a < b) as i64,
+                    _ => (a == b) as i64,
+                };
+                cpu.memory.set(target, value);
+            }
+            3 => {
+                let target = original_address(cpu, 1, modes[0]);
+                let value = cpu.input.pop_front().expect("Ran out of input");
+                cpu.memory.set(target, value);
+            }
+            4 => {
+                let value = original_parameter(cpu, 1, modes[0]);
+                cpu.output.push(value);
+            }
+            5 | 6 => {
+                let condition = original_parameter(cpu, 1, modes[0]) != 0;
+                if condition == (opcode % 100 == 5) {
+                    cpu.pc = original_parameter(cpu, 2, modes[1]) as usize;
+                    continue;
+                }
+            }
+            9 => cpu.relative_base += original_parameter(cpu, 1, modes[0]),
+            _ => cpu.running = false,
+        }
+        cpu.pc += size;
+    }
+}
+
+fn run_decoded(cpu: &mut IntCodeCPU) {
+    cpu.run().expect("Program failed");
+}
+
+// Day 2 part 2, thousands of short runs of a program with no loops
+fn noun_verb_search(program: &[i64], run: fn(&mut IntCodeCPU)) -> i64 {
+    for noun in 0..100 {
+        for verb in 0..100 {
+            let mut memory = program.to_vec();
+            memory[1] = noun;
+            memory[2] = verb;
+            let mut cpu = IntCodeCPU::new(memory);
+            run(&mut cpu);
+            if cpu.memory[0] == 19690720 {
+                return noun * 100 + verb;
+            }
+        }
+    }
+    0
+}
+
+// One long run round a tight loop, which is where caching the decode should pay off
+fn countdown(program: &[i64], run: fn(&mut IntCodeCPU)) -> i64 {
+    let mut cpu = IntCodeCPU::new(program.to_vec());
+    run(&mut cpu);
+    cpu.memory[0]
+}
+
+// The same, but compiled up front.  Compiling is part of the cost, it's not free
+fn countdown_compiled(program: &[i64]) -> i64 {
+    let compiled = CompiledProgram::compile(program);
+    let mut cpu = compiled.start(vec![]);
+    cpu.run().expect("Program failed");
+    cpu.memory()[0]
+}
+
+fn time(f: impl Fn() -> i64) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(f());
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn compare(
+    name: &str,
+    (before_name, before): (&str, impl Fn() -> i64),
+    (after_name, after): (&str, impl Fn() -> i64),
+) {
+    assert_eq!(before(), after(), "{} gives different answers", name);
+    let before = time(before);
+    let after = time(after);
+    println!(
+        "{:<20} {} {:>10.3} ms   {} {:>10.3} ms   {:.2}x",
+        name,
+        before_name,
+        before.as_secs_f64() * 1000.0,
+        after_name,
+        after.as_secs_f64() * 1000.0,
+        before.as_secs_f64() / after.as_secs_f64()
+    );
+}
+
+fn main() {
+    match fs::read_to_string("input/intcode/day02.txt") {
+        Ok(input) => {
+            let program = parse_program(&input);
+            compare(
+                "day 2 part 2",
+                ("original", || noun_verb_search(&program, run_original)),
+                ("decoded", || noun_verb_search(&program, run_decoded)),
+            );
+        }
+        Err(_) => println!("No day 2 input, skipping the noun/verb search"),
+    }
+    /* The data is up front behind a jump, the way a lot of real programs lay it out, and tmp starts
+     * off looking like an ADD, so only compiling what's really code matters here */
+    let program = assemble(
+        "           JT #1, #loop
+            tmp:    DATA 1
+            n:      DATA 1000000
+            loop:   ADD [n], #-1 -> [n]
+                    MUL [n], #3 -> [tmp]
+                    LT [tmp], #1000 -> [tmp]
+                    JT [n], #loop
+                    HLT",
+    )
+    .expect("Benchmark program assembles");
+    compare(
+        "countdown loop",
+        ("original", || countdown(&program, run_original)),
+        ("decoded", || countdown(&program, run_decoded)),
+    );
+    compare(
+        "countdown loop",
+        ("decoded", || countdown(&program, run_decoded)),
+        ("compiled", || countdown_compiled(&program)),
+    );
+}
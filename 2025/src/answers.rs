@@ -0,0 +1,243 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/* The answers we've already had accepted, so the runner can tell us straight away if a change to
+ * utils or the IntCodeCPU has broken a day that used to work.  They live in a small TOML file, one
+ * table per day, with the answers kept as strings so it doesn't matter what type a solver returns
+ *
+ *   [2019.2]
+ *   part1 = "3562672"
+ *   part2 = "8250"
+ *
+ * We only need that much TOML, so rather than pulling in a crate we read and write it ourselves.
+ * Each table can also have an input = "..." fingerprint of the input the answers came from, so if
+ * we swap an input file we know the answers we've got are for something else */
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Correct,
+    Wrong { expected: String },
+    Unknown,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Correct => write!(f, "correct"),
+            Verdict::Wrong { expected } => write!(f, "WRONG, expected {}", expected),
+            Verdict::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Answers {
+    // Keyed by year, day and part
+    answers: BTreeMap<(u16, u8, u8), String>,
+    // Keyed by year and day
+    inputs: BTreeMap<(u16, u8), String>,
+}
+
+/* FNV-1a, which is tiny and, unlike the standard library's hasher, gives the same answer on every
+ * version of Rust, which matters when the result is saved in a file */
+pub fn fingerprint(input: &str) -> String {
+    let hash = input.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+fn invalid(line: usize, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("answers line {}: {}", line + 1, message),
+    )
+}
+
+impl Answers {
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut answers = Answers::default();
+        let mut day = None;
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                let (year, d) = header
+                    .split_once('.')
+                    .ok_or_else(|| invalid(number, "expected [year.day]"))?;
+                day = Some((
+                    year.trim()
+                        .parse()
+                        .map_err(|_| invalid(number, "bad year"))?,
+                    d.trim().parse().map_err(|_| invalid(number, "bad day"))?,
+                ));
+                continue;
+            }
+            let (year, d) = day.ok_or_else(|| invalid(number, "answer before any [year.day]"))?;
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid(number, "expected partN = \"answer\""))?;
+            let value = value
+                .trim()
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .ok_or_else(|| invalid(number, "answers must be quoted"))?;
+            if key.trim() == "input" {
+                answers.inputs.insert((year, d), value.to_string());
+                continue;
+            }
+            let part = key
+                .trim()
+                .strip_prefix("part")
+                .and_then(|p| p.parse().ok())
+                .ok_or_else(|| invalid(number, "expected input, part1 or part2"))?;
+            answers.record(year, d, part, value);
+        }
+        Ok(answers)
+    }
+
+    // A missing file just means we haven't recorded anything yet
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => Answers::parse(&text),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Answers::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_toml())
+    }
+
+    pub fn to_toml(&self) -> String {
+        let mut text = String::new();
+        let mut current = None;
+        for ((year, day, part), answer) in &self.answers {
+            if current != Some((year, day)) {
+                if current.is_some() {
+                    text.push('\n');
+                }
+                text += &format!("[{}.{}]\n", year, day);
+                if let Some(input) = self.inputs.get(&(*year, *day)) {
+                    text += &format!("input = \"{}\"\n", input);
+                }
+                current = Some((year, day));
+            }
+            text += &format!("part{} = \"{}\"\n", part, answer);
+        }
+        text
+    }
+
+    // Only if we know which input the answers came from and it wasn't this one
+    pub fn input_changed(&self, year: u16, day: u8, input: &str) -> bool {
+        self.inputs
+            .get(&(year, day))
+            .is_some_and(|recorded| *recorded != fingerprint(input))
+    }
+
+    pub fn record_input(&mut self, year: u16, day: u8, input: &str) {
+        self.inputs.insert((year, day), fingerprint(input));
+    }
+
+    // The answers for a day are no use once its input has changed
+    pub fn forget(&mut self, year: u16, day: u8) {
+        self.answers.retain(|(y, d, _), _| (*y, *d) != (year, day));
+        self.inputs.remove(&(year, day));
+    }
+
+    pub fn check(&self, year: u16, day: u8, part: u8, answer: &str) -> Verdict {
+        match self.answers.get(&(year, day, part)) {
+            Some(expected) if expected == answer => Verdict::Correct,
+            Some(expected) => Verdict::Wrong {
+                expected: expected.clone(),
+            },
+            None => Verdict::Unknown,
+        }
+    }
+
+    pub fn record(&mut self, year: u16, day: u8, part: u8, answer: &str) {
+        self.answers.insert((year, day, part), answer.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::answers::*;
+
+    #[test]
+    fn test_answers_parse() {
+        let answers = Answers::parse(
+            "# Checked against the website
+            [2019.2]
+            part1 = \"3562672\"
+            part2 = \"8250\"
+
+            [2025.1]
+            part1 = \"1150\"",
+        )
+        .unwrap();
+        assert_eq!(answers.check(2019, 2, 1, "3562672"), Verdict::Correct);
+        assert_eq!(
+            answers.check(2019, 2, 2, "8251"),
+            Verdict::Wrong {
+                expected: "8250".to_string()
+            }
+        );
+        assert_eq!(answers.check(2025, 1, 2, "6738"), Verdict::Unknown);
+    }
+
+    #[test]
+    fn test_answers_round_trip() {
+        let mut answers = Answers::default();
+        answers.record(2025, 1, 2, "6738");
+        answers.record(2019, 5, 1, "9654885");
+        answers.record(2025, 1, 1, "1150");
+        let text = answers.to_toml();
+        assert_eq!(
+            text,
+            "[2019.5]\npart1 = \"9654885\"\n\n[2025.1]\npart1 = \"1150\"\npart2 = \"6738\"\n"
+        );
+        assert_eq!(Answers::parse(&text).unwrap(), answers);
+    }
+
+    #[test]
+    fn test_answers_inputs() {
+        let mut answers = Answers::default();
+        answers.record(2025, 1, 1, "1150");
+        // We can't tell whether an input has changed until we've recorded one
+        assert!(!answers.input_changed(2025, 1, "R10"));
+        answers.record_input(2025, 1, "L68\nL30");
+        assert!(!answers.input_changed(2025, 1, "L68\nL30"));
+        assert!(answers.input_changed(2025, 1, "L68\nL31"));
+        let text = answers.to_toml();
+        assert_eq!(
+            text,
+            format!(
+                "[2025.1]\ninput = \"{}\"\npart1 = \"1150\"\n",
+                fingerprint("L68\nL30")
+            )
+        );
+        assert_eq!(Answers::parse(&text).unwrap(), answers);
+        answers.forget(2025, 1);
+        assert_eq!(answers, Answers::default());
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        assert_eq!(fingerprint(""), "cbf29ce484222325");
+        assert_eq!(fingerprint("a"), "af63dc4c8601ec8c");
+    }
+
+    #[test]
+    fn test_answers_bad_file() {
+        assert!(Answers::parse("part1 = \"1\"").is_err());
+        assert!(Answers::parse("[2025]\npart1 = \"1\"").is_err());
+        assert!(Answers::parse("[2025.1]\npart1 = 1").is_err());
+        assert!(Answers::parse("[2025.1]\nanswer = \"1\"").is_err());
+    }
+}
@@ -0,0 +1,137 @@
+use std::fmt;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/* Timing a part once and printing whole milliseconds tells us nothing when most days take
+ * microseconds, so for --bench we run each part lots of times and look at the spread */
+
+// Pick a unit so there are always a few significant figures
+pub fn format_duration(duration: Duration) -> String {
+    let nanos = duration.as_nanos();
+    if nanos < 1_000 {
+        format!("{} ns", nanos)
+    } else if nanos < 1_000_000 {
+        format!("{:.2} µs", nanos as f64 / 1e3)
+    } else if nanos < 1_000_000_000 {
+        format!("{:.2} ms", nanos as f64 / 1e6)
+    } else {
+        format!("{:.2} s", nanos as f64 / 1e9)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timings {
+    // Always sorted, fastest first
+    samples: Vec<Duration>,
+}
+
+impl Timings {
+    pub fn from_samples(mut samples: Vec<Duration>) -> Self {
+        assert!(!samples.is_empty(), "Need at least one sample");
+        samples.sort();
+        Timings { samples }
+    }
+
+    /* Run f untimed warmup times, so caches and the branch predictor have settled down, and then
+     * time it runs times.  We hand back the answer from the last run so it can be checked */
+    pub fn measure<T>(warmup: usize, runs: usize, mut f: impl FnMut() -> T) -> (T, Timings) {
+        for _ in 0..warmup {
+            black_box(f());
+        }
+        let mut samples = Vec::with_capacity(runs);
+        let mut answer = None;
+        for _ in 0..runs.max(1) {
+            let start = Instant::now();
+            let result = black_box(f());
+            samples.push(start.elapsed());
+            answer = Some(result);
+        }
+        (answer.unwrap(), Timings::from_samples(samples))
+    }
+
+    pub fn runs(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn min(&self) -> Duration {
+        self.samples[0]
+    }
+
+    pub fn median(&self) -> Duration {
+        let middle = self.samples.len() / 2;
+        if self.samples.len().is_multiple_of(2) {
+            (self.samples[middle - 1] + self.samples[middle]) / 2
+        } else {
+            self.samples[middle]
+        }
+    }
+
+    pub fn mean(&self) -> Duration {
+        self.samples.iter().sum::<Duration>() / self.samples.len() as u32
+    }
+
+    // Nearest rank, so with fewer than 20 runs this is just the slowest
+    pub fn p95(&self) -> Duration {
+        let rank = (self.samples.len() * 95).div_ceil(100);
+        self.samples[rank.max(1) - 1]
+    }
+}
+
+impl fmt::Display for Timings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "min {}  median {}  mean {}  p95 {}  ({} runs)",
+            format_duration(self.min()),
+            format_duration(self.median()),
+            format_duration(self.mean()),
+            format_duration(self.p95()),
+            self.runs()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bench::*;
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_nanos(850)), "850 ns");
+        assert_eq!(format_duration(Duration::from_nanos(12_345)), "12.35 µs");
+        assert_eq!(format_duration(Duration::from_micros(4_500)), "4.50 ms");
+        assert_eq!(format_duration(Duration::from_millis(2_250)), "2.25 s");
+    }
+
+    #[test]
+    fn test_timings_statistics() {
+        let timings = Timings::from_samples(
+            [5, 1, 4, 2, 3, 100]
+                .into_iter()
+                .map(Duration::from_micros)
+                .collect(),
+        );
+        assert_eq!(timings.min(), Duration::from_micros(1));
+        assert_eq!(
+            timings.median(),
+            Duration::from_micros(3) + Duration::from_nanos(500)
+        );
+        assert_eq!(timings.mean(), Duration::from_micros(115) / 6);
+        assert_eq!(timings.p95(), Duration::from_micros(100));
+        // With 20 runs, the 95th percentile is the 19th fastest
+        let timings = Timings::from_samples((1..=20).map(Duration::from_micros).collect());
+        assert_eq!(timings.p95(), Duration::from_micros(19));
+        assert_eq!(timings.median(), Duration::from_nanos(10_500));
+    }
+
+    #[test]
+    fn test_timings_measure() {
+        let mut calls = 0;
+        let (answer, timings) = Timings::measure(2, 5, || {
+            calls += 1;
+            calls
+        });
+        assert_eq!(answer, 7);
+        assert_eq!(timings.runs(), 5);
+    }
+}
@@ -0,0 +1,51 @@
+use clap::Parser;
+use mbsaoc2025::intcode::arcade::{Arcade, Autopilot, Tile};
+use mbsaoc2025::intcode::parse_program;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+/// Play the day 13 arcade game on autopilot
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    filename: String,
+    /// Just draw the screen without putting any quarters in
+    #[arg(short, long)]
+    demo: bool,
+    /// Play every frame back in the terminal once the game is over, this many milliseconds apart
+    #[arg(short, long)]
+    watch: Option<u64>,
+    /// Save every frame as a PPM image in this directory
+    #[arg(short, long)]
+    frames: Option<String>,
+    /// How many pixels across each tile is in the saved images
+    #[arg(short, long, default_value_t = 8)]
+    scale: usize,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Args = Args::parse();
+    let mut arcade = Arcade::new(parse_program(&fs::read_to_string(&args.filename)?));
+    if !args.demo {
+        arcade.insert_quarters();
+    }
+    if args.watch.is_some() || args.frames.is_some() {
+        arcade.record_frames();
+    }
+    let score = arcade.play(&mut Autopilot)?;
+    if let Some(delay) = args.watch {
+        for frame in arcade.frames.iter().flatten() {
+            frame.show(&mut io::stdout())?;
+            thread::sleep(Duration::from_millis(delay));
+        }
+    }
+    if let Some(directory) = &args.frames {
+        arcade.save_frames(directory, args.scale)?;
+    }
+    println!("Blocks left: {}", arcade.screen.count(Tile::Block));
+    println!("Final score: {}", score);
+    Ok(())
+}
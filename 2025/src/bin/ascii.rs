@@ -0,0 +1,20 @@
+use clap::Parser;
+use mbsaoc2025::intcode::ascii::AsciiCPU;
+use mbsaoc2025::intcode::intcodecpu::IntCodeCPU;
+use mbsaoc2025::intcode::parse_program;
+use std::fs;
+use std::io;
+
+/// Talk to an ASCII intcode program from the terminal, like the day 25 text adventure
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    filename: String,
+}
+
+fn main() -> io::Result<()> {
+    let args: Args = Args::parse();
+    let program = parse_program(&fs::read_to_string(&args.filename)?);
+    let mut ascii = AsciiCPU::new(IntCodeCPU::new(program));
+    ascii.interactive(io::stdin().lock(), io::stdout())
+}
@@ -0,0 +1,44 @@
+use clap::Parser;
+use mbsaoc2025::intcode::debugger::Debugger;
+use mbsaoc2025::intcode::intcodecpu::IntCodeCPU;
+use mbsaoc2025::intcode::parse_program;
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+/// Step through an intcode program with breakpoints and watchpoints
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    filename: String,
+    /// Values to queue up as input before we start
+    #[arg(short, long, value_delimiter = ',', allow_negative_numbers = true)]
+    input: Vec<i64>,
+    /// Addresses to break at
+    #[arg(short, long)]
+    r#break: Vec<usize>,
+    /// How many instructions to remember for the trace
+    #[arg(short, long, default_value_t = 20)]
+    trace: usize,
+}
+
+fn main() -> io::Result<()> {
+    let args: Args = Args::parse();
+    let program = parse_program(&fs::read_to_string(&args.filename)?);
+    let mut debugger = Debugger::new(IntCodeCPU::new_with_io(program, args.input), args.trace);
+    debugger.breakpoints.extend(args.r#break);
+    print!("{}", debugger.list(0, 1));
+    let stdin = io::stdin();
+    loop {
+        print!("(icd) ");
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            break;
+        }
+        match debugger.command(&line) {
+            Some(text) => print!("{}", text),
+            None => break,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,18 @@
+use clap::Parser;
+use mbsaoc2025::intcode::disassembler::disassemble;
+use mbsaoc2025::intcode::parse_program;
+use std::fs;
+
+/// Print an annotated listing of an intcode program
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    filename: String,
+}
+
+fn main() -> std::io::Result<()> {
+    let args: Args = Args::parse();
+    let program = parse_program(&fs::read_to_string(&args.filename)?);
+    print!("{}", disassemble(&program));
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use clap::Parser;
+use mbsaoc2025::intcode::ascii::AsciiCPU;
+use mbsaoc2025::intcode::intcodecpu::IntCodeCPU;
+use mbsaoc2025::intcode::io::{ReaderInput, WriterOutput};
+use mbsaoc2025::intcode::parse_program;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::process::ExitCode;
+
+/// Run any intcode program, printing its outputs as they happen
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    filename: String,
+    /// Patch memory before we start, like --set 1=12 --set 2=2 for day 2
+    #[arg(short, long, value_parser = parse_patch)]
+    set: Vec<(usize, i64)>,
+    /// Values to queue up as input, anything more the program wants is read from stdin
+    #[arg(short, long, value_delimiter = ',', allow_negative_numbers = true)]
+    input: Vec<i64>,
+    /// Talk to the program in text rather than numbers
+    #[arg(short, long)]
+    ascii: bool,
+    /// Memory cells to print once the program has finished
+    #[arg(short, long, value_delimiter = ',')]
+    memory: Vec<usize>,
+    /// Print a profile of what the program spent its time doing
+    #[arg(short, long)]
+    profile: bool,
+    /// Print the profile as JSON instead
+    #[arg(long)]
+    profile_json: bool,
+}
+
+fn parse_patch(text: &str) -> Result<(usize, i64), String> {
+    let (address, value) = text
+        .split_once('=')
+        .ok_or_else(|| format!("expected address=value, got {}", text))?;
+    Ok((
+        address
+            .trim()
+            .parse()
+            .map_err(|_| format!("bad address {}", address))?,
+        value
+            .trim()
+            .parse()
+            .map_err(|_| format!("bad value {}", value))?,
+    ))
+}
+
+fn run(args: Args) -> Result<(), Box<dyn Error>> {
+    let mut cpu = IntCodeCPU::new_with_io(
+        parse_program(&fs::read_to_string(&args.filename)?),
+        args.input,
+    );
+    for (address, value) in args.set {
+        cpu.memory.set(address, value);
+    }
+    if args.profile || args.profile_json {
+        cpu.enable_profiling();
+    }
+    if args.ascii {
+        let mut ascii = AsciiCPU::new(cpu);
+        ascii.interactive(io::stdin().lock(), io::stdout())?;
+        cpu = ascii.cpu;
+    } else {
+        cpu.run_with(ReaderInput::stdin(), WriterOutput::stdout())?;
+    }
+    for address in args.memory {
+        println!("[{}] = {}", address, cpu.memory.get(address));
+    }
+    if let Some(profile) = cpu.profile() {
+        if args.profile_json {
+            println!("{}", profile.to_json());
+        } else {
+            print!("{}", profile);
+        }
+    }
+    Ok(())
+}
+
+// Errors from the program itself read better through Display than the Debug that main would give us
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -1,4 +1,7 @@
-fn parse_input(_input: &str) -> Vec<i32> {
+use mbsaoc2025::register_solver;
+use mbsaoc2025::solver::{Example, Solver};
+
+pub fn parse_input(_input: &str) -> Vec<i32> {
     _input
         .lines()
         .map(|line| line.trim())
@@ -44,29 +47,69 @@ fn execute_instructions_part2(start: i32, instructions: &Vec<i32>) -> Vec<i32> {
     }
     results
 }
-/*
-* day2, Part 1.
-*/
-pub fn calculate_part1(input: &str) -> usize {
-    execute_instructions(50, &parse_input(input))
-        .into_iter()
-        .filter(|&x| x == 0)
-        .count()
-}
 
-/*
- * day2, Part 2
- *
- */
-pub fn calculate_part2(input: &str) -> usize {
-    execute_instructions_part2(50, &parse_input(input))
-        .into_iter()
-        .sum::<i32>() as usize
+const EXAMPLE: &str = "L68
+L30
+R48
+L5
+R60
+L55
+L1
+L99
+R14
+L82";
+
+pub struct Day1;
+
+impl Solver for Day1 {
+    const YEAR: u16 = 2025;
+    const DAY: u8 = 1;
+    const NAME: &'static str = "2025 Day 1";
+    const INPUT: &'static str = "input/2025/day01.txt";
+
+    type Input = Vec<i32>;
+    type Part1 = usize;
+    type Part2 = i32;
+
+    const EXAMPLES: &'static [Example] = &[
+        Example::part1(EXAMPLE, "3"),
+        Example::part2(EXAMPLE, "6"),
+        // A full turn and a bit passes 0 three times whichever way we go
+        Example::part2("L320", "3"),
+        Example::part2("R320", "3"),
+    ];
+
+    fn parse(input: &str) -> Vec<i32> {
+        parse_input(input)
+    }
+
+    /*
+     * day1, Part 1.
+     */
+    fn part1(instructions: &Vec<i32>) -> usize {
+        execute_instructions(50, instructions)
+            .into_iter()
+            .filter(|&x| x == 0)
+            .count()
+    }
+
+    /*
+     * day1, Part 2
+     *
+     */
+    fn part2(instructions: &Vec<i32>) -> i32 {
+        execute_instructions_part2(50, instructions)
+            .into_iter()
+            .sum::<i32>()
+    }
 }
 
+register_solver!(Day1);
+
 #[cfg(test)]
 mod tests {
     use crate::day1::*;
+    use mbsaoc2025::solver::check_examples;
 
     #[test]
     fn test_parse() {
@@ -81,7 +124,7 @@ L99
 R14
 L82";
         assert_eq!(
-            parse_input(&input),
+            parse_input(input),
             vec![-68, -30, 48, -5, 60, -55, -1, -99, 14, -82]
         );
     }
@@ -124,33 +167,7 @@ L82";
     }
 
     #[test]
-    fn test_example_part1() {
-        let input = "L68
-L30
-R48
-L5
-R60
-L55
-L1
-L99
-R14
-L82";
-        assert_eq!(calculate_part1(&input), 3);
-    }
-    #[test]
-    fn test_example_part2() {
-        let input = "L68
-L30
-R48
-L5
-R60
-L55
-L1
-L99
-R14
-L82";
-        assert_eq!(calculate_part2(&input), 6);
-        assert_eq!(calculate_part2("L320"), 3);
-        assert_eq!(calculate_part2("R320"), 3);
+    fn test_examples() {
+        check_examples::<Day1>();
     }
 }
@@ -1,3 +1,6 @@
+use mbsaoc2025::register_solver;
+use mbsaoc2025::solver::{Example, Solver};
+
 pub fn parse_id_range(id_range: &str) -> (u64, u64) {
     let mut ids = id_range
         .split("-")
@@ -5,13 +8,13 @@ pub fn parse_id_range(id_range: &str) -> (u64, u64) {
     (ids.next().unwrap(), ids.next().unwrap())
 }
 
-fn parse_input(input: &str) -> Vec<(u64, u64)> {
+pub fn parse_input(input: &str) -> Vec<(u64, u64)> {
     input.split(",").map(parse_id_range).collect()
 }
 
 pub fn has_repeated_sequence_twice(input: &str) -> bool {
     let (a, b) = input.split_at(input.len() / 2);
-    return a == b;
+    a == b
 }
 
 pub fn generate_id_sequence(start: u64, end: u64) -> Vec<String> {
@@ -23,25 +26,19 @@ pub fn generate_id_sequence(start: u64, end: u64) -> Vec<String> {
     (start..end + 1).map(|digit| digit.to_string()).collect()
 }
 
-pub fn find_invalid_ids(ids: Vec<(u64, u64)>) -> Vec<u64> {
+pub fn find_invalid_ids(ids: &[(u64, u64)]) -> Vec<u64> {
     ids.iter()
         .map(|(start, end)| generate_id_sequence(*start, *end))
-        .map(|id_sequence| {
+        .flat_map(|id_sequence| {
             id_sequence
                 .iter()
-                .filter(|id| has_repeated_sequence_twice(&id))
+                .filter(|id| has_repeated_sequence_twice(id))
                 .map(|id| id.parse::<u64>().expect("Expected integer ids"))
                 .collect::<Vec<u64>>()
         })
-        .flatten()
         .collect()
 }
 
-pub fn calculate_part1(input: &str) -> usize {
-    let ids = parse_input(input);
-    find_invalid_ids(ids).into_iter().sum::<u64>() as usize
-}
-
 pub fn is_only_repeats(substr: &str, id: &str) -> bool {
     let mut remainder = id.to_string();
     if substr == id {
@@ -50,7 +47,7 @@ pub fn is_only_repeats(substr: &str, id: &str) -> bool {
     while remainder.starts_with(substr) {
         remainder = remainder.split_at(substr.len()).1.to_string()
     }
-    return remainder == "";
+    remainder.is_empty()
 }
 
 pub fn generate_substrings(id: &str) -> Vec<&str> {
@@ -67,30 +64,59 @@ pub fn has_repeating_substring(id: &str) -> bool {
         .any(|substr| is_only_repeats(substr, id))
 }
 
-pub fn find_invalid_ids_with_repeating_substrings(ids: Vec<(u64, u64)>) -> Vec<u64> {
+pub fn find_invalid_ids_with_repeating_substrings(ids: &[(u64, u64)]) -> Vec<u64> {
     ids.iter()
         .map(|(start, end)| generate_id_sequence(*start, *end))
-        .map(|id_sequence| {
+        .flat_map(|id_sequence| {
             id_sequence
                 .iter()
-                .filter(|id| has_repeating_substring(&id))
+                .filter(|id| has_repeating_substring(id))
                 .map(|id| id.parse::<u64>().expect("Expected integer ids"))
                 .collect::<Vec<u64>>()
         })
-        .flatten()
         .collect()
 }
 
-pub fn calculate_part2(input: &str) -> usize {
-    let ids = parse_input(input);
-    find_invalid_ids_with_repeating_substrings(ids)
-        .into_iter()
-        .sum::<u64>() as usize
+const EXAMPLE: &str = "11-22,95-115,998-1012,1188511880-1188511890,222220-222224,1698522-1698528,446443-446449,38593856-38593862,565653-565659,824824821-824824827,2121212118-2121212124";
+
+pub struct Day2;
+
+impl Solver for Day2 {
+    const YEAR: u16 = 2025;
+    const DAY: u8 = 2;
+    const NAME: &'static str = "2025 Day 2";
+    const INPUT: &'static str = "input/2025/day02.txt";
+
+    type Input = Vec<(u64, u64)>;
+    type Part1 = u64;
+    type Part2 = u64;
+
+    const EXAMPLES: &'static [Example] = &[
+        Example::part1(EXAMPLE, "1227775554"),
+        Example::part2(EXAMPLE, "4174379265"),
+    ];
+
+    fn parse(input: &str) -> Vec<(u64, u64)> {
+        parse_input(input)
+    }
+
+    fn part1(ids: &Vec<(u64, u64)>) -> u64 {
+        find_invalid_ids(ids).into_iter().sum()
+    }
+
+    fn part2(ids: &Vec<(u64, u64)>) -> u64 {
+        find_invalid_ids_with_repeating_substrings(ids)
+            .into_iter()
+            .sum()
+    }
 }
 
+register_solver!(Day2);
+
 #[cfg(test)]
 mod tests {
     use crate::day2::*;
+    use mbsaoc2025::solver::check_examples;
 
     #[test]
     fn test_has_repeated_sequence_twice() {
@@ -145,7 +171,10 @@ mod tests {
 
     #[test]
     fn test_parse_input() {
-        for (expected, input) in [(
+        assert_eq!(
+            parse_input(
+                "11-22,95-115,998-1012,1188511880-1188511890,38593856-38593862,979959461-980003045"
+            ),
             vec![
                 (11, 22),
                 (95, 115),
@@ -153,27 +182,16 @@ mod tests {
                 (1188511880, 1188511890),
                 (38593856, 38593862),
                 (979959461, 980003045),
-            ],
-            "11-22,95-115,998-1012,1188511880-1188511890,38593856-38593862,979959461-980003045",
-        )] {
-            assert_eq!(parse_input(input), expected);
-        }
+            ]
+        );
     }
 
     #[test]
     fn test_find_invalid_ids() {
-        for (expected, input) in [(
-            vec![11, 22, 99, 1010, 1188511885],
-            vec![(11, 22), (95, 115), (998, 1012), (1188511880, 1188511890)],
-        )] {
-            assert_eq!(find_invalid_ids(input), expected);
-        }
-    }
-
-    #[test]
-    fn test_calculate_part1() {
-        let input = "11-22,95-115,998-1012,1188511880-1188511890,222220-222224,1698522-1698528,446443-446449,38593856-38593862,565653-565659,824824821-824824827,2121212118-2121212124";
-        assert_eq!(calculate_part1(input), 1227775554);
+        assert_eq!(
+            find_invalid_ids(&[(11, 22), (95, 115), (998, 1012), (1188511880, 1188511890)]),
+            vec![11, 22, 99, 1010, 1188511885]
+        );
     }
 
     #[test]
@@ -233,8 +251,7 @@ mod tests {
         }
     }
     #[test]
-    fn test_calculate_part2() {
-        let input = "11-22,95-115,998-1012,1188511880-1188511890,222220-222224,1698522-1698528,446443-446449,38593856-38593862,565653-565659,824824821-824824827,2121212118-2121212124";
-        assert_eq!(calculate_part2(input), 4174379265);
+    fn test_examples() {
+        check_examples::<Day2>();
     }
 }
@@ -1,4 +1,7 @@
-fn find_largest_pair(line: &Vec<u32>) -> u32 {
+use mbsaoc2025::register_solver;
+use mbsaoc2025::solver::{Example, Solver};
+
+fn find_largest_pair(line: &[u32]) -> u32 {
     line.iter()
         .enumerate()
         .map(
@@ -11,20 +14,49 @@ fn find_largest_pair(line: &Vec<u32>) -> u32 {
         .unwrap()
 }
 
-pub fn calculate_part1(input: &str) -> usize {
+pub fn parse_input(input: &str) -> Vec<Vec<u32>> {
     input
         .lines()
-        .map(|line| find_largest_pair(&line.chars().map(|c| c.to_digit(10).unwrap()).collect()))
-        .sum::<u32>() as usize
+        .map(|line| line.chars().map(|c| c.to_digit(10).unwrap()).collect())
+        .collect()
 }
 
-pub fn calculate_part2(_input: &str) -> usize {
-    0
+pub struct Day3;
+
+impl Solver for Day3 {
+    const YEAR: u16 = 2025;
+    const DAY: u8 = 3;
+    const NAME: &'static str = "2025 Day 3";
+    const INPUT: &'static str = "input/2025/day03.txt";
+
+    type Input = Vec<Vec<u32>>;
+    type Part1 = u32;
+    type Part2 = u32;
+
+    const EXAMPLES: &'static [Example] = &[Example::part1(
+        "987654321111111\n811111111111119\n234234234234278\n818181911112111",
+        "357",
+    )];
+
+    fn parse(input: &str) -> Vec<Vec<u32>> {
+        parse_input(input)
+    }
+
+    fn part1(banks: &Vec<Vec<u32>>) -> u32 {
+        banks.iter().map(|bank| find_largest_pair(bank)).sum()
+    }
+
+    fn part2(_banks: &Vec<Vec<u32>>) -> u32 {
+        0
+    }
 }
 
+register_solver!(Day3);
+
 #[cfg(test)]
 mod tests {
     use crate::day3::*;
+    use mbsaoc2025::solver::check_examples;
 
     #[test]
     fn test_find_largest_pair() {
@@ -37,4 +69,9 @@ mod tests {
             assert_eq!(find_largest_pair(&input), expected);
         }
     }
+
+    #[test]
+    fn test_examples() {
+        check_examples::<Day3>();
+    }
 }
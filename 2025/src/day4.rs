@@ -1,6 +1,7 @@
 use std::collections::HashSet;
 
-use clap::value_parser;
+use mbsaoc2025::register_solver;
+use mbsaoc2025::solver::{Example, Solver};
 
 use crate::utils::Coordinate;
 
@@ -20,7 +21,7 @@ fn count_neighbours(grid: &HashSet<Coordinate>, location: &Coordinate) -> usize
     neighbours(location).intersection(grid).count()
 }
 
-fn parse_grid(input: &str) -> HashSet<Coordinate> {
+pub fn parse_grid(input: &str) -> HashSet<Coordinate> {
     input
         .lines()
         .enumerate()
@@ -33,34 +34,68 @@ fn parse_grid(input: &str) -> HashSet<Coordinate> {
         .collect::<HashSet<_, _>>()
 }
 
-pub fn calculate_part1(input: &str) -> usize {
-    let grid = parse_grid(input);
-    grid.iter()
-        .filter(|coord| count_neighbours(&grid, coord) < 4)
-        .count()
-}
+const EXAMPLE: &str = "..@@.@@@@.
+@@@.@.@.@@
+@@@@@.@.@@
+@.@@@@..@.
+@@.@@@@.@@
+.@@@@@@@.@
+.@.@.@.@@@
+@.@@@.@@@@
+.@@@@@@@@.
+@.@.@@@.@.";
+
+pub struct Day4;
+
+impl Solver for Day4 {
+    const YEAR: u16 = 2025;
+    const DAY: u8 = 4;
+    const NAME: &'static str = "2025 Day 4";
+    const INPUT: &'static str = "input/2025/day04.txt";
+
+    type Input = HashSet<Coordinate>;
+    type Part1 = usize;
+    type Part2 = usize;
 
-pub fn calculate_part2(input: &str) -> usize {
-    let mut grid = parse_grid(input);
-    let mut has_valid_neighbours = true;
-    let mut total = 0;
-    let mut valid_neighbours = HashSet::new();
-    while has_valid_neighbours {
-        valid_neighbours = grid
-            .iter()
-            .filter(|coord| count_neighbours(&grid, coord) < 4)
-            .collect();
-        has_valid_neighbours = valid_neighbours.len() > 0;
-        total += valid_neighbours.len();
-        grid = grid
-            .iter()
-            .filter(|coordinate| !valid_neighbours.contains(coordinate))
-            .copied()
-            .collect();
+    const EXAMPLES: &'static [Example] = &[
+        Example::part1(EXAMPLE, "13"),
+        Example::part2("..@.\n@...\n..@.\n.@..", "4"),
+        Example::part2("@@@.\n@@@.\n@@@.\n....", "9"),
+    ];
+
+    fn parse(input: &str) -> HashSet<Coordinate> {
+        parse_grid(input)
+    }
+
+    fn part1(grid: &HashSet<Coordinate>) -> usize {
+        grid.iter()
+            .filter(|coord| count_neighbours(grid, coord) < 4)
+            .count()
+    }
+
+    fn part2(grid: &HashSet<Coordinate>) -> usize {
+        let mut grid = grid.clone();
+        let mut has_valid_neighbours = true;
+        let mut total = 0;
+        while has_valid_neighbours {
+            let valid_neighbours: HashSet<_> = grid
+                .iter()
+                .filter(|coord| count_neighbours(&grid, coord) < 4)
+                .collect();
+            has_valid_neighbours = !valid_neighbours.is_empty();
+            total += valid_neighbours.len();
+            grid = grid
+                .iter()
+                .filter(|coordinate| !valid_neighbours.contains(coordinate))
+                .copied()
+                .collect();
+        }
+        total
     }
-    total
 }
 
+register_solver!(Day4);
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashSet;
@@ -68,6 +103,7 @@ mod tests {
     use assert_unordered::assert_eq_unordered;
 
     use crate::{day4::*, utils::Coordinate};
+    use mbsaoc2025::solver::check_examples;
 
     #[test]
     fn test_find_neighbours() {
@@ -148,25 +184,7 @@ mod tests {
     }
 
     #[test]
-    fn test_part1() {
-        let input = "..@@.@@@@.
-@@@.@.@.@@
-@@@@@.@.@@
-@.@@@@..@.
-@@.@@@@.@@
-.@@@@@@@.@
-.@.@.@.@@@
-@.@@@.@@@@
-.@@@@@@@@.
-@.@.@@@.@.";
-        assert_eq!(13, calculate_part1(input));
-    }
-
-    #[test]
-    fn test_part2() {
-        let input = "..@.\n@...\n..@.\n.@..";
-        assert_eq!(4, calculate_part2(input));
-        let input = "@@@.\n@@@.\n@@@.\n....";
-        assert_eq!(9, calculate_part2(input));
+    fn test_examples() {
+        check_examples::<Day4>();
     }
 }
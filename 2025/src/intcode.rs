@@ -1,3 +1,27 @@
+pub mod arcade;
+pub mod ascii;
+pub mod assembler;
+pub mod circuit;
+pub mod compiler;
+pub mod decode;
+pub mod debugger;
+pub mod disassembler;
 pub mod intcode2;
+pub mod memory;
 pub mod intcodecpu;
-pub mod intcode5;
\ No newline at end of file
+pub mod io;
+pub mod network;
+pub mod profile;
+pub mod robot;
+pub mod snapshot;
+pub mod symbolic;
+pub mod intcode5;
+
+// Intcode programs are just a single line of comma separated numbers
+pub fn parse_program(input: &str) -> Vec<i64> {
+    input
+        .trim()
+        .split(',')
+        .map(|s| s.trim().parse::<i64>().expect("Failed to parse integer"))
+        .collect()
+}
@@ -0,0 +1,290 @@
+use crate::intcode::intcodecpu::{IntCodeCPU, IntCodeError, RunStatus};
+use crate::utils::Coordinate;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tile {
+    Empty,
+    Wall,
+    Block,
+    Paddle,
+    Ball,
+}
+
+impl Tile {
+    // Anything we don't recognise is drawn as empty rather than stopping the game
+    pub fn from(value: i64) -> Self {
+        match value {
+            1 => Tile::Wall,
+            2 => Tile::Block,
+            3 => Tile::Paddle,
+            4 => Tile::Ball,
+            _ => Tile::Empty,
+        }
+    }
+
+    pub fn to_char(&self) -> char {
+        match self {
+            Tile::Empty => ' ',
+            Tile::Wall => '#',
+            Tile::Block => '=',
+            Tile::Paddle => '-',
+            Tile::Ball => 'o',
+        }
+    }
+
+    fn colour(&self) -> [u8; 3] {
+        match self {
+            Tile::Empty => [0, 0, 0],
+            Tile::Wall => [128, 128, 128],
+            Tile::Block => [200, 80, 40],
+            Tile::Paddle => [240, 240, 240],
+            Tile::Ball => [240, 220, 0],
+        }
+    }
+}
+
+// Everything the cabinet has drawn so far.  Tiles that have never been drawn are empty
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Screen {
+    pub tiles: HashMap<Coordinate, Tile>,
+    pub score: i64,
+}
+
+impl Screen {
+    pub fn count(&self, tile: Tile) -> usize {
+        self.tiles.values().filter(|t| **t == tile).count()
+    }
+
+    // Where the first tile of a kind is, which for the ball and paddle is the only one
+    pub fn find(&self, tile: Tile) -> Option<Coordinate> {
+        self.tiles
+            .iter()
+            .find(|(_, t)| **t == tile)
+            .map(|(position, _)| *position)
+    }
+
+    // The game always draws from (0,0), so the size is just one past the furthest tile
+    pub fn size(&self) -> (usize, usize) {
+        let width = self.tiles.keys().map(|c| c.x + 1).max().unwrap_or(0);
+        let height = self.tiles.keys().map(|c| c.y + 1).max().unwrap_or(0);
+        (width as usize, height as usize)
+    }
+
+    fn tile(&self, x: usize, y: usize) -> Tile {
+        self.tiles
+            .get(&Coordinate::new_usize(x, y))
+            .copied()
+            .unwrap_or(Tile::Empty)
+    }
+
+    pub fn render(&self) -> String {
+        let (width, height) = self.size();
+        let mut text = format!("Score: {}\n", self.score);
+        for y in 0..height {
+            let line: String = (0..width).map(|x| self.tile(x, y).to_char()).collect();
+            text += line.trim_end();
+            text.push('\n');
+        }
+        text
+    }
+
+    // Clear the terminal and draw over the top, so a run of frames plays like an animation
+    pub fn show(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "\x1b[H\x1b[2J{}", self.render())?;
+        out.flush()
+    }
+
+    /* A binary PPM image, each tile drawn as a scale by scale square.  Nothing can read them without
+     * an image viewer, but nearly everything can read PPM and we don't need any crates to write one */
+    pub fn to_ppm(&self, scale: usize) -> Vec<u8> {
+        let (width, height) = self.size();
+        let mut image = format!("P6\n{} {}\n255\n", width * scale, height * scale).into_bytes();
+        for y in 0..height * scale {
+            for x in 0..width * scale {
+                image.extend(self.tile(x / scale, y / scale).colour());
+            }
+        }
+        image
+    }
+}
+
+// Something that decides which way to push the joystick, -1 for left, 0 to leave it and 1 for right
+pub trait Joystick {
+    fn tilt(&mut self, screen: &Screen) -> i64;
+}
+
+// Keeps the paddle under the ball, which is enough to never lose
+pub struct Autopilot;
+
+impl Joystick for Autopilot {
+    fn tilt(&mut self, screen: &Screen) -> i64 {
+        match (screen.find(Tile::Ball), screen.find(Tile::Paddle)) {
+            (Some(ball), Some(paddle)) => (ball.x - paddle.x).signum() as i64,
+            _ => 0,
+        }
+    }
+}
+
+/* The day 13 cabinet.  The program draws with triples of x, y and tile, except for x of -1 which
+ * sets the score, and reads the joystick whenever it's ready for the next frame */
+pub struct Arcade {
+    pub cpu: IntCodeCPU,
+    pub screen: Screen,
+    // If we're recording, what the screen looked like every time the game asked for input, and at the end
+    pub frames: Option<Vec<Screen>>,
+}
+
+impl Arcade {
+    pub fn new(program: Vec<i64>) -> Self {
+        Arcade {
+            cpu: IntCodeCPU::new(program),
+            screen: Screen::default(),
+            frames: None,
+        }
+    }
+
+    // Part 2, setting address 0 to 2 lets us play for free
+    pub fn insert_quarters(&mut self) {
+        self.cpu.memory.set(0, 2);
+    }
+
+    pub fn record_frames(&mut self) {
+        self.frames = Some(vec![]);
+    }
+
+    fn draw(&mut self, x: i64, y: i64, value: i64) {
+        if x == -1 && y == 0 {
+            self.screen.score = value;
+        } else {
+            let position = Coordinate::new(x as i32, y as i32);
+            self.screen.tiles.insert(position, Tile::from(value));
+        }
+    }
+
+    fn capture(&mut self) {
+        if let Some(frames) = &mut self.frames {
+            frames.push(self.screen.clone());
+        }
+    }
+
+    // Play until the game is over, returning the final score
+    pub fn play(&mut self, joystick: &mut impl Joystick) -> Result<i64, IntCodeError> {
+        let mut pending = Vec::with_capacity(3);
+        loop {
+            match self.cpu.resume()? {
+                RunStatus::Output(value) => {
+                    pending.push(value);
+                    if let [x, y, value] = pending[..] {
+                        self.draw(x, y, value);
+                        pending.clear();
+                    }
+                }
+                RunStatus::NeedsInput => {
+                    self.capture();
+                    let tilt = joystick.tilt(&self.screen);
+                    self.cpu.push_input(tilt);
+                }
+                RunStatus::Halted => {
+                    self.capture();
+                    return Ok(self.screen.score);
+                }
+                RunStatus::Running => {}
+            }
+        }
+    }
+
+    // Write every recorded frame out as frame0000.ppm, frame0001.ppm and so on
+    pub fn save_frames(&self, directory: impl AsRef<Path>, scale: usize) -> io::Result<()> {
+        fs::create_dir_all(&directory)?;
+        for (index, frame) in self.frames.iter().flatten().enumerate() {
+            let path = directory.as_ref().join(format!("frame{:04}.ppm", index));
+            fs::write(path, frame.to_ppm(scale))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::arcade::*;
+    use crate::intcode::assembler::assemble;
+
+    /* A very short game.  It draws a wall, a block, the ball and the paddle, moves the paddle however
+     * the joystick says, and scores 1000 for every step right */
+    fn game() -> Vec<i64> {
+        assemble(
+            "   OUT #0
+                OUT #0
+                OUT #1
+                OUT #1
+                OUT #0
+                OUT #2
+                OUT #2
+                OUT #1
+                OUT #4
+                OUT #0
+                OUT #2
+                OUT #3
+                IN -> [j]
+                OUT #0
+                OUT #2
+                OUT #0
+                OUT [j]
+                OUT #2
+                OUT #3
+                MUL [j], #1000 -> [j]
+                OUT #-1
+                OUT #0
+                OUT [j]
+                HLT
+        j:      DATA 0",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_arcade_autopilot() {
+        let mut arcade = Arcade::new(game());
+        assert_eq!(arcade.play(&mut Autopilot), Ok(1000));
+        assert_eq!(
+            arcade.screen.find(Tile::Paddle),
+            Some(Coordinate::new(1, 2))
+        );
+        assert_eq!(arcade.screen.count(Tile::Block), 1);
+        assert_eq!(arcade.screen.render(), "Score: 1000\n#=\n  o\n -\n");
+    }
+
+    #[test]
+    fn test_arcade_frames() {
+        // Leaving the joystick alone scores nothing
+        struct Neutral;
+        impl Joystick for Neutral {
+            fn tilt(&mut self, _screen: &Screen) -> i64 {
+                0
+            }
+        }
+        let mut arcade = Arcade::new(game());
+        arcade.record_frames();
+        assert_eq!(arcade.play(&mut Neutral), Ok(0));
+        let frames = arcade.frames.as_ref().unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].render(), "Score: 0\n#=\n  o\n-\n");
+        assert_eq!(frames[0], frames[1]);
+    }
+
+    #[test]
+    fn test_screen_ppm() {
+        let mut arcade = Arcade::new(game());
+        arcade.play(&mut Autopilot).unwrap();
+        let image = arcade.screen.to_ppm(2);
+        let header = b"P6\n6 6\n255\n";
+        assert!(image.starts_with(header));
+        assert_eq!(image.len(), header.len() + 6 * 6 * 3);
+        // The top left pixel is part of the wall
+        assert_eq!(image[header.len()..header.len() + 3], [128, 128, 128]);
+    }
+}
@@ -0,0 +1,225 @@
+use crate::intcode::intcodecpu::{IntCodeCPU, IntCodeError, RunStatus};
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+/* Everything we got back from the program since we last asked.  Values 0 to 255 are characters (the
+ * Latin-1 ones past 127 included, so nothing a program outputs as a byte is lost), anything above
+ * that is the answer to the puzzle and goes in numbers rather than being mangled into text.  Negative
+ * values can't be characters either, so they go in numbers too */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Response {
+    pub text: String,
+    pub numbers: Vec<i64>,
+    pub halted: bool,
+}
+
+// A character we can't send, because its code is past 255 and the program would never read it as one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsendableChar(pub char);
+
+impl fmt::Display for UnsendableChar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "can't send {:?}, only characters up to 255 fit", self.0)
+    }
+}
+
+impl std::error::Error for UnsendableChar {}
+
+// Wraps a CPU that talks in ASCII, so we can deal in lines of text rather than codes
+pub struct AsciiCPU {
+    pub cpu: IntCodeCPU,
+}
+
+impl AsciiCPU {
+    pub fn new(cpu: IntCodeCPU) -> Self {
+        AsciiCPU { cpu }
+    }
+
+    // Nothing is sent if any of the text can't be, so the program never sees half a command
+    pub fn send(&mut self, text: &str) -> Result<(), UnsendableChar> {
+        if let Some(c) = text.chars().find(|c| u8::try_from(*c).is_err()) {
+            return Err(UnsendableChar(c));
+        }
+        for c in text.chars() {
+            self.cpu.push_input(c as i64);
+        }
+        Ok(())
+    }
+
+    // Programs read a line at a time, so every command needs a newline on the end
+    pub fn send_line(&mut self, line: &str) -> Result<(), UnsendableChar> {
+        self.send(line)?;
+        self.cpu.push_input('\n' as i64);
+        Ok(())
+    }
+
+    // Run, collecting output, until the program wants input, halts, or done says we've got enough
+    fn read(&mut self, done: impl Fn(&str) -> bool) -> Result<Response, IntCodeError> {
+        let mut response = Response::default();
+        loop {
+            match self.cpu.resume()? {
+                RunStatus::Output(value) => match u8::try_from(value) {
+                    Ok(c) => {
+                        response.text.push(char::from(c));
+                        if done(&response.text) {
+                            return Ok(response);
+                        }
+                    }
+                    _ => response.numbers.push(value),
+                },
+                RunStatus::NeedsInput => return Ok(response),
+                RunStatus::Halted => {
+                    response.halted = true;
+                    return Ok(response);
+                }
+                RunStatus::Running => {}
+            }
+        }
+    }
+
+    // Everything up to the next time it wants input
+    pub fn read_all(&mut self) -> Result<Response, IntCodeError> {
+        self.read(|_| false)
+    }
+
+    // A single line, without the newline on the end
+    pub fn read_line(&mut self) -> Result<Response, IntCodeError> {
+        let mut response = self.read(|text| text.ends_with('\n'))?;
+        if response.text.ends_with('\n') {
+            response.text.pop();
+        }
+        Ok(response)
+    }
+
+    // Everything up to and including the prompt, like "Command?" in the day 25 adventure
+    pub fn read_until(&mut self, prompt: &str) -> Result<Response, IntCodeError> {
+        self.read(|text| text.ends_with(prompt))
+    }
+
+    /* Hook the program up to a terminal (or anything else that reads and writes), so we can play
+     * it by hand.  Runs until the program halts or we run out of input.  A line we can't send gets
+     * an explanation and we wait for another one */
+    pub fn interactive(&mut self, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        let mut lines = input.lines();
+        loop {
+            let response = self.read_all().map_err(io::Error::other)?;
+            write!(output, "{}", response.text)?;
+            for number in response.numbers {
+                writeln!(output, "[{}]", number)?;
+            }
+            output.flush()?;
+            if response.halted {
+                return Ok(());
+            }
+            loop {
+                let Some(line) = lines.next() else {
+                    return Ok(());
+                };
+                match self.send_line(line?.trim_end()) {
+                    Ok(()) => break,
+                    Err(error) => {
+                        writeln!(output, "{}", error)?;
+                        output.flush()?;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::ascii::*;
+    use crate::intcode::assembler::assemble;
+
+    /* Asks for a name, echoes it back and then outputs the sum of the characters, which for
+     * anything more than a couple of letters is too big to be a character */
+    fn greeter() -> IntCodeCPU {
+        let prompt: String = "Name?\n"
+            .chars()
+            .map(|c| format!("OUT #{}\n", c as i64))
+            .collect();
+        let source = prompt
+            + "
+            loop:   IN -> [c]
+                    EQ [c], #10 -> [tmp]
+                    JT [tmp], #done
+                    ADD [sum], [c] -> [sum]
+                    OUT [c]
+                    JT #1, #loop
+            done:   OUT #10
+                    OUT [sum]
+                    HLT
+            c:      DATA 0
+            tmp:    DATA 0
+            sum:    DATA 0";
+        IntCodeCPU::new(assemble(&source).unwrap())
+    }
+
+    #[test]
+    fn test_ascii_conversation() {
+        let mut ascii = AsciiCPU::new(greeter());
+        assert_eq!(ascii.read_line().unwrap().text, "Name?");
+        assert_eq!(ascii.read_all().unwrap(), Response::default());
+        ascii.send_line("Hello").unwrap();
+        assert_eq!(
+            ascii.read_all().unwrap(),
+            Response {
+                text: "Hello\n".to_string(),
+                numbers: vec![500],
+                halted: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_ascii_character_range() {
+        // Everything that fits in a byte is text, past that or below 0 it's a number
+        let mut ascii = AsciiCPU::new(IntCodeCPU::new(vec![
+            104, 127, 104, 128, 104, 255, 104, 256, 104, -1, 99,
+        ]));
+        assert_eq!(
+            ascii.read_all().unwrap(),
+            Response {
+                text: "\u{7f}\u{80}\u{ff}".to_string(),
+                numbers: vec![256, -1],
+                halted: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_ascii_send_range() {
+        let mut ascii = AsciiCPU::new(greeter());
+        ascii.send("a\u{ff}").unwrap();
+        assert_eq!(ascii.cpu.input, vec![97, 255]);
+        // None of it goes if any of it can't
+        assert_eq!(ascii.send_line("b\u{100}"), Err(UnsendableChar('\u{100}')));
+        assert_eq!(ascii.cpu.input, vec![97, 255]);
+    }
+
+    #[test]
+    fn test_ascii_read_until() {
+        let mut ascii = AsciiCPU::new(greeter());
+        assert_eq!(ascii.read_until("me").unwrap().text, "Name");
+        assert_eq!(ascii.read_until("me").unwrap().text, "?\n");
+    }
+
+    #[test]
+    fn test_ascii_interactive() {
+        let mut ascii = AsciiCPU::new(greeter());
+        let mut output = vec![];
+        ascii.interactive("Bob\n".as_bytes(), &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "Name?\nBob\n[275]\n");
+        // A line with a character we can't send is explained and skipped
+        let mut ascii = AsciiCPU::new(greeter());
+        let mut output = vec![];
+        ascii
+            .interactive("B\u{2603}b\nBob\n".as_bytes(), &mut output)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Name?\ncan't send '\u{2603}', only characters up to 255 fit\nBob\n[275]\n"
+        );
+    }
+}
@@ -0,0 +1,403 @@
+use crate::intcode::decode::Instruction;
+use crate::intcode::intcodecpu::Modes;
+use std::collections::HashMap;
+use std::fmt;
+
+/* The assembly language is the same one the disassembler prints, so a listing can be fed straight back in.
+ *
+ *   loop:   ADD [counter], #-1 -> [counter]   ; comments run to the end of the line
+ *           JT [counter], #loop
+ *           OUT [rb+2]
+ *           HLT
+ *   counter: DATA 3
+ *
+ * #n is immediate, [n] is position and [rb+n] is relative.  Anywhere a number goes, a label
+ * (optionally with +n or -n on the end) can go instead */
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssemblerErrorKind {
+    UnknownMnemonic(String),
+    WrongOperandCount { expected: usize, found: usize },
+    MissingTarget,
+    UnexpectedTarget,
+    ImmediateWrite,
+    BadOperand(String),
+    UnknownLabel(String),
+    DuplicateLabel(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssemblerError {
+    // 1 based, like an editor would show it
+    pub line: usize,
+    pub kind: AssemblerErrorKind,
+}
+
+impl fmt::Display for AssemblerErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssemblerErrorKind::UnknownMnemonic(m) => write!(f, "unknown mnemonic {}", m),
+            AssemblerErrorKind::WrongOperandCount { expected, found } => {
+                write!(f, "expected {} operands but found {}", expected, found)
+            }
+            AssemblerErrorKind::MissingTarget => write!(f, "missing -> target"),
+            AssemblerErrorKind::UnexpectedTarget => write!(f, "instruction doesn't write anywhere"),
+            AssemblerErrorKind::ImmediateWrite => write!(f, "write target can't be immediate"),
+            AssemblerErrorKind::BadOperand(o) => write!(f, "can't understand operand {}", o),
+            AssemblerErrorKind::UnknownLabel(l) => write!(f, "unknown label {}", l),
+            AssemblerErrorKind::DuplicateLabel(l) => write!(f, "label {} defined twice", l),
+        }
+    }
+}
+
+impl fmt::Display for AssemblerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
+}
+
+impl std::error::Error for AssemblerError {}
+
+// A number, or a label we'll only know the address of once we've seen the whole program
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+    Number(i64),
+    Label(String, i64),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Operand {
+    mode: Modes,
+    value: Value,
+}
+
+enum Item {
+    Instruction { opcode: i64, operands: Vec<Operand> },
+    Data(Vec<Value>),
+}
+
+// The base opcodes we know about, we let the decoder tell us their mnemonic and shape
+const OPCODES: [i64; 10] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 99];
+
+fn lookup(mnemonic: &str) -> Option<(i64, Instruction)> {
+    OPCODES.iter().find_map(|&opcode| {
+        let op = Instruction::decode(opcode).expect("Base opcodes are always valid");
+        (op.operation.mnemonic() == mnemonic).then_some((opcode, op))
+    })
+}
+
+fn is_label(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && name != "rb"
+}
+
+fn parse_value(text: &str) -> Result<Value, AssemblerErrorKind> {
+    let text = text.trim();
+    if let Ok(n) = text.parse::<i64>() {
+        return Ok(Value::Number(n));
+    }
+    // Split off any offset, searching from 1 so we don't split a leading minus sign
+    let (name, offset) = match text.get(1..).and_then(|t| t.find(['+', '-'])) {
+        Some(i) => {
+            let (name, offset) = text.split_at(i + 1);
+            let offset = offset.replace('+', "");
+            let offset = offset
+                .trim()
+                .parse::<i64>()
+                .map_err(|_| AssemblerErrorKind::BadOperand(text.to_string()))?;
+            (name.trim(), offset)
+        }
+        None => (text, 0),
+    };
+    if is_label(name) {
+        Ok(Value::Label(name.to_string(), offset))
+    } else {
+        Err(AssemblerErrorKind::BadOperand(text.to_string()))
+    }
+}
+
+fn parse_operand(text: &str) -> Result<Operand, AssemblerErrorKind> {
+    let text = text.trim();
+    let bad = || AssemblerErrorKind::BadOperand(text.to_string());
+    if let Some(value) = text.strip_prefix('#') {
+        return Ok(Operand {
+            mode: Modes::Immediate,
+            value: parse_value(value).map_err(|_| bad())?,
+        });
+    }
+    let inner = text
+        .strip_prefix('[')
+        .and_then(|t| t.strip_suffix(']'))
+        .ok_or_else(bad)?
+        .trim();
+    if let Some(offset) = inner.strip_prefix("rb") {
+        let offset: String = offset.split_whitespace().collect();
+        let value = if offset.is_empty() {
+            Value::Number(0)
+        } else if let Some(offset) = offset.strip_prefix('+') {
+            parse_value(offset).map_err(|_| bad())?
+        } else if offset.starts_with('-') {
+            parse_value(&offset).map_err(|_| bad())?
+        } else {
+            return Err(bad());
+        };
+        return Ok(Operand {
+            mode: Modes::Relative,
+            value,
+        });
+    }
+    Ok(Operand {
+        mode: Modes::Position,
+        value: parse_value(inner).map_err(|_| bad())?,
+    })
+}
+
+fn split_list(text: &str) -> Vec<&str> {
+    let text = text.trim();
+    if text.is_empty() {
+        vec![]
+    } else {
+        text.split(',').map(|s| s.trim()).collect()
+    }
+}
+
+fn parse_instruction(mnemonic: &str, rest: &str) -> Result<Item, AssemblerErrorKind> {
+    let (opcode, op) = lookup(&mnemonic.to_ascii_uppercase())
+        .ok_or_else(|| AssemblerErrorKind::UnknownMnemonic(mnemonic.to_string()))?;
+    let (sources, target) = match rest.split_once("->") {
+        Some((sources, target)) => (sources, Some(target)),
+        None => (rest, None),
+    };
+    let mut operands = split_list(sources)
+        .into_iter()
+        .map(parse_operand)
+        .collect::<Result<Vec<_>, _>>()?;
+    match (op.operation.write_parameter(), target) {
+        (Some(n), Some(target)) => {
+            let target = parse_operand(target)?;
+            if target.mode == Modes::Immediate {
+                return Err(AssemblerErrorKind::ImmediateWrite);
+            }
+            if operands.len() != n - 1 {
+                return Err(AssemblerErrorKind::WrongOperandCount {
+                    expected: n - 1,
+                    found: operands.len(),
+                });
+            }
+            operands.push(target);
+        }
+        (Some(_), None) => return Err(AssemblerErrorKind::MissingTarget),
+        (None, Some(_)) => return Err(AssemblerErrorKind::UnexpectedTarget),
+        (None, None) => {}
+    }
+    let expected = op.size - 1;
+    if operands.len() != expected {
+        return Err(AssemblerErrorKind::WrongOperandCount {
+            expected,
+            found: operands.len(),
+        });
+    }
+    Ok(Item::Instruction { opcode, operands })
+}
+
+fn resolve(value: &Value, labels: &HashMap<String, usize>) -> Result<i64, AssemblerErrorKind> {
+    match value {
+        Value::Number(n) => Ok(*n),
+        Value::Label(name, offset) => labels
+            .get(name)
+            .map(|address| *address as i64 + offset)
+            .ok_or_else(|| AssemblerErrorKind::UnknownLabel(name.clone())),
+    }
+}
+
+fn mode_digit(mode: Modes) -> i64 {
+    match mode {
+        Modes::Position => 0,
+        Modes::Immediate => 1,
+        Modes::Relative => 2,
+    }
+}
+
+pub fn assemble(source: &str) -> Result<Vec<i64>, AssemblerError> {
+    // First pass, parse every line and work out where each label lands
+    let mut items = vec![];
+    let mut labels = HashMap::new();
+    let mut address = 0;
+    for (number, line) in source.lines().enumerate() {
+        let error = |kind| AssemblerError {
+            line: number + 1,
+            kind,
+        };
+        let mut text = line.split(';').next().unwrap_or("").trim();
+        if let Some((label, rest)) = text.split_once(':')
+            && is_label(label.trim())
+        {
+            let label = label.trim().to_string();
+            if labels.insert(label.clone(), address).is_some() {
+                return Err(error(AssemblerErrorKind::DuplicateLabel(label)));
+            }
+            text = rest.trim();
+        }
+        if text.is_empty() {
+            continue;
+        }
+        let (mnemonic, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let item = if mnemonic.eq_ignore_ascii_case("DATA") {
+            Item::Data(
+                split_list(rest)
+                    .into_iter()
+                    .map(parse_value)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(error)?,
+            )
+        } else {
+            parse_instruction(mnemonic, rest).map_err(error)?
+        };
+        address += match &item {
+            Item::Instruction { operands, .. } => operands.len() + 1,
+            Item::Data(values) => values.len(),
+        };
+        items.push((number + 1, item));
+    }
+
+    // Second pass, now we know all the labels we can write out the words
+    let mut program = Vec::with_capacity(address);
+    for (line, item) in items {
+        let error = |kind| AssemblerError { line, kind };
+        match item {
+            Item::Instruction { opcode, operands } => {
+                let modes = operands
+                    .iter()
+                    .enumerate()
+                    .map(|(i, o)| mode_digit(o.mode) * 10_i64.pow(i as u32 + 2))
+                    .sum::<i64>();
+                program.push(opcode + modes);
+                for operand in operands {
+                    program.push(resolve(&operand.value, &labels).map_err(error)?);
+                }
+            }
+            Item::Data(values) => {
+                for value in values {
+                    program.push(resolve(&value, &labels).map_err(error)?);
+                }
+            }
+        }
+    }
+    Ok(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::assembler::*;
+    use crate::intcode::disassembler::disassemble;
+    use crate::intcode::intcodecpu::IntCodeCPU;
+
+    #[test]
+    fn test_assemble_simple() {
+        assert_eq!(
+            assemble("ADD [5], #7 -> [9]\nHLT").unwrap(),
+            vec![1001, 5, 7, 9, 99]
+        );
+        assert_eq!(
+            assemble("MUL [4], #3 -> [4]\nDATA 33").unwrap(),
+            vec![1002, 4, 3, 4, 33]
+        );
+        assert_eq!(
+            assemble("in -> [rb+3]\nout [rb-2]\narb #-1\njf [rb], #0").unwrap(),
+            vec![203, 3, 204, -2, 109, -1, 1206, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_assemble_labels() {
+        let source = "
+            ; Count down from 3, outputting as we go
+            loop:   OUT [counter]
+                    ADD [counter], #-1 -> [counter]
+                    JT [counter], #loop
+                    HLT
+            counter: DATA 3
+            table:
+                    DATA counter, table+1, loop-1
+        ";
+        let program = assemble(source).unwrap();
+        assert_eq!(
+            program,
+            vec![4, 10, 1001, 10, -1, 10, 1005, 10, 0, 99, 3, 10, 12, -1]
+        );
+        let mut cpu = IntCodeCPU::new(program);
+        cpu.run().unwrap();
+        assert_eq!(cpu.output, vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_assemble_errors() {
+        for (source, line, expected) in [
+            (
+                "HLT\nFOO #1",
+                2,
+                AssemblerErrorKind::UnknownMnemonic("FOO".to_string()),
+            ),
+            (
+                "ADD #1 -> [0]",
+                1,
+                AssemblerErrorKind::WrongOperandCount {
+                    expected: 2,
+                    found: 1,
+                },
+            ),
+            ("ADD #1, #2", 1, AssemblerErrorKind::MissingTarget),
+            ("OUT #1 -> [0]", 1, AssemblerErrorKind::UnexpectedTarget),
+            ("IN -> #4", 1, AssemblerErrorKind::ImmediateWrite),
+            ("OUT 5", 1, AssemblerErrorKind::BadOperand("5".to_string())),
+            (
+                "OUT [rb*2]",
+                1,
+                AssemblerErrorKind::BadOperand("[rb*2]".to_string()),
+            ),
+            (
+                "JT #1, #nowhere",
+                1,
+                AssemblerErrorKind::UnknownLabel("nowhere".to_string()),
+            ),
+            (
+                "a: HLT\na: HLT",
+                2,
+                AssemblerErrorKind::DuplicateLabel("a".to_string()),
+            ),
+        ] {
+            assert_eq!(
+                assemble(source),
+                Err(AssemblerError {
+                    line,
+                    kind: expected
+                }),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn test_assemble_round_trip() {
+        let day5 = std::fs::read_to_string("input/intcode/day05.txt").unwrap();
+        for program in [
+            vec![1001, 5, 7, 9, 99, 3, 0, 0, 0, 0],
+            vec![
+                109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+            ],
+            vec![21101, 7, 0, 0, 1105, 1, 8, 99, 2106, 0, 0],
+            vec![
+                3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36,
+                98, 0, 0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000,
+                1, 20, 4, 20, 1105, 1, 46, 98, 99,
+            ],
+            crate::intcode::parse_program(&day5),
+        ] {
+            let listing = disassemble(&program).to_string();
+            assert_eq!(assemble(&listing).unwrap(), program, "{}", listing);
+        }
+    }
+}
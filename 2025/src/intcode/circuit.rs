@@ -0,0 +1,285 @@
+use crate::intcode::intcodecpu::{IntCodeCPU, IntCodeError, IntCodeErrorKind, RunStatus};
+use crate::intcode::io::OutputFn;
+use itertools::Itertools;
+use std::fmt;
+use std::sync::mpsc;
+use std::thread;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitError {
+    // One of the CPUs crashed, which one and why
+    Cpu(usize, IntCodeError),
+    // Every CPU is waiting for input and nobody is going to send any
+    Deadlock,
+    // The last CPU halted without ever producing anything
+    NoOutput,
+    // There aren't any CPUs to run
+    Empty,
+}
+
+impl fmt::Display for CircuitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CircuitError::Cpu(index, error) => write!(f, "cpu {}: {}", index, error),
+            CircuitError::Deadlock => write!(f, "every cpu is waiting for input"),
+            CircuitError::NoOutput => write!(f, "circuit halted without any output"),
+            CircuitError::Empty => write!(f, "circuit has no cpus"),
+        }
+    }
+}
+
+impl std::error::Error for CircuitError {}
+
+/* A set of CPUs all running the same program, wired output to input in a line.  With feedback the
+ * last one's output goes back round to the first, and we keep going until the last one halts */
+pub struct Circuit {
+    cpus: Vec<IntCodeCPU>,
+    feedback: bool,
+}
+
+impl Circuit {
+    pub fn new(count: usize, program: &[i64]) -> Self {
+        Circuit {
+            cpus: (0..count)
+                .map(|_| IntCodeCPU::new(program.to_vec()))
+                .collect(),
+            feedback: false,
+        }
+    }
+
+    pub fn new_feedback(count: usize, program: &[i64]) -> Self {
+        Circuit {
+            feedback: true,
+            ..Circuit::new(count, program)
+        }
+    }
+
+    // The phase should be the same length as the number of CPUs, each gets its phase as its first input
+    pub fn set_phase(&mut self, phase: &[i64]) {
+        for (cpu, phase) in self.cpus.iter_mut().zip(phase) {
+            cpu.push_input(*phase);
+        }
+    }
+
+    /* Feed the input into the first CPU and run them all round robin on this thread, each one runs until
+     * it needs input it hasn't got, which keeps everything deterministic */
+    pub fn run(&mut self, input: i64) -> Result<i64, CircuitError> {
+        let count = self.cpus.len();
+        if count == 0 {
+            return Err(CircuitError::Empty);
+        }
+        let mut last_output = None;
+        self.cpus[0].push_input(input);
+        loop {
+            let mut progress = false;
+            for index in 0..count {
+                loop {
+                    let status = self.cpus[index]
+                        .resume()
+                        .map_err(|e| CircuitError::Cpu(index, e))?;
+                    match status {
+                        RunStatus::Output(value) => {
+                            progress = true;
+                            if index + 1 < count {
+                                self.cpus[index + 1].push_input(value);
+                            } else {
+                                last_output = Some(value);
+                                if self.feedback {
+                                    self.cpus[0].push_input(value);
+                                }
+                            }
+                        }
+                        RunStatus::NeedsInput | RunStatus::Halted => break,
+                        RunStatus::Running => {}
+                    }
+                }
+            }
+            if !self.cpus[count - 1].running {
+                return last_output.ok_or(CircuitError::NoOutput);
+            }
+            if !progress {
+                return Err(CircuitError::Deadlock);
+            }
+        }
+    }
+
+    pub fn run_to_completion(&mut self, phase: &[i64], input: i64) -> Result<i64, CircuitError> {
+        self.set_phase(phase);
+        self.run(input)
+    }
+
+    /* The same as run, but each CPU gets its own thread and they talk over channels, like the
+     * goroutines in the Go version.  Outputs arrive in the same order, so the answer is the same */
+    pub fn run_threaded(self, input: i64) -> Result<i64, CircuitError> {
+        let count = self.cpus.len();
+        if count == 0 {
+            return Err(CircuitError::Empty);
+        }
+        let (senders, receivers): (Vec<_>, Vec<_>) = (0..count).map(|_| mpsc::channel()).unzip();
+        senders[0]
+            .send(input)
+            .expect("First CPU is still listening");
+        let feedback = self.feedback;
+        thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .cpus
+                .into_iter()
+                .zip(receivers)
+                .enumerate()
+                .map(|(index, (mut cpu, receiver))| {
+                    let next = if index + 1 < count {
+                        Some(senders[index + 1].clone())
+                    } else if feedback {
+                        Some(senders[0].clone())
+                    } else {
+                        None
+                    };
+                    scope.spawn(move || {
+                        let mut last_output = None;
+                        let result = cpu.run_with(
+                            receiver,
+                            OutputFn(|value| {
+                                last_output = Some(value);
+                                // The next CPU might already have halted, that's fine
+                                if let Some(next) = &next {
+                                    let _ = next.send(value);
+                                }
+                            }),
+                        );
+                        match result {
+                            Ok(()) => Ok(last_output),
+                            // Our channel closed with nothing in it, so nobody is ever going to send anything
+                            Err(e) if e.kind == IntCodeErrorKind::InputUnderflow => {
+                                Err(CircuitError::Deadlock)
+                            }
+                            Err(e) => Err(CircuitError::Cpu(index, e)),
+                        }
+                    })
+                })
+                .collect();
+            // Once every thread has its own sender we drop ours, so a starved CPU sees a closed channel
+            drop(senders);
+            let results: Vec<_> = handles
+                .into_iter()
+                .map(|h| h.join().expect("Circuit thread panicked"))
+                .collect();
+            let mut last = None;
+            for result in results {
+                last = result?;
+            }
+            last.ok_or(CircuitError::NoOutput)
+        })
+    }
+}
+
+/* Try every ordering of the phases and return the one that gives the biggest signal from the end
+ * of the circuit, along with that signal */
+pub fn best_phase(
+    program: &[i64],
+    phases: &[i64],
+    feedback: bool,
+) -> Result<(Vec<i64>, i64), CircuitError> {
+    let mut best: Option<(Vec<i64>, i64)> = None;
+    for phase in phases.iter().copied().permutations(phases.len()) {
+        let mut circuit = if feedback {
+            Circuit::new_feedback(phases.len(), program)
+        } else {
+            Circuit::new(phases.len(), program)
+        };
+        let signal = circuit.run_to_completion(&phase, 0)?;
+        if best.as_ref().is_none_or(|(_, b)| signal > *b) {
+            best = Some((phase, signal));
+        }
+    }
+    best.ok_or(CircuitError::NoOutput)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::circuit::*;
+    use crate::intcode::parse_program;
+
+    const EXAMPLE1: &str = "3,15,3,16,1002,16,10,16,1,16,15,15,4,15,99,0,0";
+    const EXAMPLE2: &str =
+        "3,23,3,24,1002,24,10,24,1002,23,-1,23,101,5,23,23,1,24,23,23,4,23,99,0,0";
+    const EXAMPLE3: &str = "3,31,3,32,1002,32,10,32,1001,31,-2,31,1007,31,0,33,1002,33,7,33,1,33,31,31,1,32,31,31,4,31,99,0,0,0";
+    const FEEDBACK1: &str =
+        "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5";
+    const FEEDBACK2: &str = "3,52,1001,52,-5,52,3,53,1,52,56,54,1007,54,5,55,1005,55,26,1001,54,-5,54,1105,1,12,1,53,54,53,1008,54,0,55,1001,55,1,55,2,53,55,53,4,53,1001,56,-1,56,1005,56,6,99,0,0,0,0,10";
+
+    #[test]
+    fn test_circuit_chain() {
+        for (program, phase, expected) in [
+            (EXAMPLE1, vec![4, 3, 2, 1, 0], 43210),
+            (EXAMPLE2, vec![0, 1, 2, 3, 4], 54321),
+            (EXAMPLE3, vec![1, 0, 4, 3, 2], 65210),
+        ] {
+            let mut circuit = Circuit::new(5, &parse_program(program));
+            assert_eq!(circuit.run_to_completion(&phase, 0), Ok(expected));
+        }
+    }
+
+    #[test]
+    fn test_circuit_feedback() {
+        for (program, phase, expected) in [
+            (FEEDBACK1, vec![9, 8, 7, 6, 5], 139629729),
+            (FEEDBACK2, vec![9, 7, 8, 5, 6], 18216),
+        ] {
+            let mut circuit = Circuit::new_feedback(5, &parse_program(program));
+            assert_eq!(circuit.run_to_completion(&phase, 0), Ok(expected));
+        }
+    }
+
+    #[test]
+    fn test_circuit_threaded() {
+        let mut circuit = Circuit::new(5, &parse_program(EXAMPLE3));
+        circuit.set_phase(&[1, 0, 4, 3, 2]);
+        assert_eq!(circuit.run_threaded(0), Ok(65210));
+        let mut circuit = Circuit::new_feedback(5, &parse_program(FEEDBACK2));
+        circuit.set_phase(&[9, 7, 8, 5, 6]);
+        assert_eq!(circuit.run_threaded(0), Ok(18216));
+    }
+
+    #[test]
+    fn test_best_phase() {
+        assert_eq!(
+            best_phase(&parse_program(EXAMPLE1), &[0, 1, 2, 3, 4], false),
+            Ok((vec![4, 3, 2, 1, 0], 43210))
+        );
+        assert_eq!(
+            best_phase(&parse_program(EXAMPLE2), &[0, 1, 2, 3, 4], false),
+            Ok((vec![0, 1, 2, 3, 4], 54321))
+        );
+        assert_eq!(
+            best_phase(&parse_program(FEEDBACK1), &[5, 6, 7, 8, 9], true),
+            Ok((vec![9, 8, 7, 6, 5], 139629729))
+        );
+    }
+
+    #[test]
+    fn test_circuit_errors() {
+        // Each CPU wants three inputs but only ever gets two, so they all end up waiting
+        let mut circuit = Circuit::new(2, &[3, 0, 3, 0, 3, 0, 99]);
+        assert_eq!(
+            circuit.run_to_completion(&[1, 2], 0),
+            Err(CircuitError::Deadlock)
+        );
+        let mut circuit = Circuit::new(2, &[3, 0, 42]);
+        match circuit.run_to_completion(&[1, 2], 0) {
+            Err(CircuitError::Cpu(0, error)) => assert_eq!(error.pc, 2),
+            result => panic!("Expected a crash, got {:?}", result),
+        }
+        let mut circuit = Circuit::new(2, &[3, 0, 99]);
+        assert_eq!(
+            circuit.run_to_completion(&[1, 2], 0),
+            Err(CircuitError::NoOutput)
+        );
+        // Nothing to run is an error rather than a panic, however we run it
+        assert_eq!(Circuit::new(0, &[99]).run(0), Err(CircuitError::Empty));
+        assert_eq!(
+            Circuit::new_feedback(0, &[99]).run_threaded(0),
+            Err(CircuitError::Empty)
+        );
+        assert_eq!(best_phase(&[99], &[], false), Err(CircuitError::Empty));
+    }
+}
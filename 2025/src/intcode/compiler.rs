@@ -0,0 +1,590 @@
+use crate::intcode::decode::{Instruction, Machine, Operation};
+use crate::intcode::disassembler::trace;
+use crate::intcode::intcodecpu::{
+    IntCodeError, IntCodeErrorKind, Modes, RunStatus, relative_address, to_address,
+};
+use crate::intcode::memory::Memory;
+use std::collections::VecDeque;
+
+/* Turning a program into Rust closures ahead of time, so running it is a chain of calls with every
+ * opcode, mode and parameter already worked out.  We only compile what the disassembler reckons is
+ * code, so data after an unconditional jump stays data.  That only holds while nobody writes over the
+ * code, so a write that lands on a compiled instruction takes just that instruction out of service
+ * and it gets interpreted from then on (unless something puts the original words back).  Anything we
+ * never compiled, like the target of a computed jump we couldn't see coming, is interpreted the same
+ * way, and as soon as we get back to a compiled instruction we carry on with that */
+
+// A parameter with its mode and value baked in
+#[derive(Debug, Clone, Copy)]
+enum Operand {
+    Immediate(i64),
+    Position(usize),
+    Relative(i64),
+}
+
+// Everything a compiled op can change
+struct State {
+    memory: Memory,
+    relative_base: i64,
+    input: VecDeque<i64>,
+}
+
+// What happened when an op ran, and so where to go next
+enum Step {
+    Next(usize),
+    NeedsInput,
+    Output(i64, usize),
+    Halt,
+    // We wrote over compiled code at the first address, so check it before going on to the second
+    Modified(usize, usize),
+    Error(IntCodeErrorKind),
+}
+
+type Op = Box<dyn Fn(&mut State, &[bool]) -> Step + Send + Sync>;
+
+impl State {
+    fn read(&self, operand: Operand) -> Result<i64, IntCodeErrorKind> {
+        Ok(match operand {
+            Operand::Immediate(value) => value,
+            Operand::Position(address) => self.memory.get(address),
+            Operand::Relative(offset) => self
+                .memory
+                .get(relative_address(self.relative_base, offset)?),
+        })
+    }
+
+    // Where the write went, if it landed on compiled code
+    fn write(
+        &mut self,
+        operand: Operand,
+        value: i64,
+        code: &[bool],
+    ) -> Result<Option<usize>, IntCodeErrorKind> {
+        let address = match operand {
+            Operand::Position(address) => address,
+            Operand::Relative(offset) => relative_address(self.relative_base, offset)?,
+            Operand::Immediate(_) => return Err(IntCodeErrorKind::ImmediateWrite),
+        };
+        self.memory.set(address, value);
+        Ok(code
+            .get(address)
+            .copied()
+            .unwrap_or(false)
+            .then_some(address))
+    }
+}
+
+fn operand(mode: Modes, value: i64) -> Option<Operand> {
+    match mode {
+        Modes::Immediate => Some(Operand::Immediate(value)),
+        Modes::Position => usize::try_from(value).ok().map(Operand::Position),
+        Modes::Relative => Some(Operand::Relative(value)),
+    }
+}
+
+macro_rules! attempt {
+    ($e:expr) => {
+        match $e {
+            Ok(value) => value,
+            Err(kind) => return Step::Error(kind),
+        }
+    };
+}
+
+/* Build the op for a single instruction.  Anything that's always going to fail, like writing to an
+ * immediate, isn't compiled, so the interpreter gets to report it properly */
+fn compile_instruction(address: usize, instruction: &Instruction, words: &[i64]) -> Option<Op> {
+    let mut operands = [Operand::Immediate(0); 3];
+    for i in 0..instruction.size - 1 {
+        operands[i] = operand(instruction.modes[i], words[i + 1])?;
+    }
+    let [a, b, c] = operands;
+    let next = address + instruction.size;
+    let write_to_immediate = matches!(c, Operand::Immediate(_));
+    // The three parameter ops only differ in how they combine their two inputs
+    let binary = |f: fn(i64, i64) -> Option<i64>| -> Option<Op> {
+        if write_to_immediate {
+            return None;
+        }
+        Some(Box::new(move |s: &mut State, code: &[bool]| {
+            let value = f(attempt!(s.read(a)), attempt!(s.read(b)));
+            let value = attempt!(value.ok_or(IntCodeErrorKind::Overflow));
+            match attempt!(s.write(c, value, code)) {
+                Some(address) => Step::Modified(address, next),
+                None => Step::Next(next),
+            }
+        }))
+    };
+    match instruction.operation {
+        Operation::Add => binary(i64::checked_add),
+        Operation::Mul => binary(i64::checked_mul),
+        Operation::LessThan => binary(|x, y| Some((x < y) as i64)),
+        Operation::Equals => binary(|x, y| Some((x == y) as i64)),
+        Operation::Input => {
+            if matches!(a, Operand::Immediate(_)) {
+                return None;
+            }
+            Some(Box::new(move |s: &mut State, code: &[bool]| {
+                let Some(value) = s.input.pop_front() else {
+                    return Step::NeedsInput;
+                };
+                match attempt!(s.write(a, value, code)) {
+                    Some(address) => Step::Modified(address, next),
+                    None => Step::Next(next),
+                }
+            }))
+        }
+        Operation::Output => Some(Box::new(move |s: &mut State, _: &[bool]| {
+            Step::Output(attempt!(s.read(a)), next)
+        })),
+        Operation::JumpIfTrue | Operation::JumpIfFalse => {
+            let when = instruction.operation == Operation::JumpIfTrue;
+            Some(Box::new(move |s: &mut State, _: &[bool]| {
+                if (attempt!(s.read(a)) != 0) == when {
+                    Step::Next(attempt!(to_address(attempt!(s.read(b)))))
+                } else {
+                    Step::Next(next)
+                }
+            }))
+        }
+        Operation::AdjustRelativeBase => Some(Box::new(move |s: &mut State, _: &[bool]| {
+            let adjustment = attempt!(s.read(a));
+            s.relative_base = attempt!(
+                s.relative_base
+                    .checked_add(adjustment)
+                    .ok_or(IntCodeErrorKind::Overflow)
+            );
+            Step::Next(next)
+        })),
+        Operation::Halt => Some(Box::new(|_: &mut State, _: &[bool]| Step::Halt)),
+    }
+}
+
+/* A compiled program, which can be run as many times as we like.  The disassembler's trace tells us
+ * where the instructions are, following jumps with immediate targets and return addresses, but not
+ * running on past a jump that's always taken */
+pub struct CompiledProgram {
+    image: Vec<i64>,
+    ops: Vec<Option<Op>>,
+    // Which words belong to a compiled instruction, so writing to them means checking that instruction
+    code: Vec<bool>,
+}
+
+impl CompiledProgram {
+    pub fn compile(program: &[i64]) -> Self {
+        let mut ops: Vec<Option<Op>> = (0..program.len()).map(|_| None).collect();
+        let mut code = vec![false; program.len()];
+        let (starts, _) = trace(program);
+        for address in starts {
+            let Ok(instruction) = Instruction::decode(program[address]) else {
+                continue;
+            };
+            let words = &program[address..address + instruction.size];
+            if let Some(op) = compile_instruction(address, &instruction, words) {
+                ops[address] = Some(op);
+                code[address..address + instruction.size].fill(true);
+            }
+        }
+        CompiledProgram {
+            image: program.to_vec(),
+            ops,
+            code,
+        }
+    }
+
+    // How many instructions we managed to compile
+    pub fn compiled(&self) -> usize {
+        self.ops.iter().filter(|op| op.is_some()).count()
+    }
+
+    pub fn start(&self, input: Vec<i64>) -> CompiledCPU<'_> {
+        CompiledCPU {
+            program: self,
+            state: State {
+                memory: Memory::new(self.image.clone()),
+                relative_base: 0,
+                input: input.into(),
+            },
+            pc: 0,
+            running: true,
+            stale: vec![],
+            interpreted: 0,
+            output: vec![],
+        }
+    }
+
+    // Run to the end with the input we're given, which is all day 19 needs, returning the outputs
+    pub fn run(&self, input: Vec<i64>) -> Result<Vec<i64>, IntCodeError> {
+        let mut cpu = self.start(input);
+        cpu.run()?;
+        Ok(cpu.output().to_vec())
+    }
+}
+
+/* One run of a compiled program, which behaves just like an IntCodeCPU so it can be driven with resume
+ * and push_input */
+pub struct CompiledCPU<'a> {
+    program: &'a CompiledProgram,
+    state: State,
+    pc: usize,
+    running: bool,
+    // Compiled instructions that have been written over, by where they start.  Empty until there's one
+    stale: Vec<bool>,
+    interpreted: usize,
+    // Only run collects outputs, the same as IntCodeCPU
+    output: Vec<i64>,
+}
+
+impl CompiledCPU<'_> {
+    // How many times we've had to interpret an instruction rather than run the compiled one
+    pub fn interpreted(&self) -> usize {
+        self.interpreted
+    }
+
+    pub fn push_input(&mut self, value: i64) {
+        self.state.input.push_back(value);
+    }
+
+    pub fn output(&self) -> &[i64] {
+        &self.output
+    }
+
+    pub fn memory(&self) -> &Memory {
+        &self.state.memory
+    }
+
+    fn error(&self, kind: IntCodeErrorKind) -> IntCodeError {
+        IntCodeError {
+            pc: self.pc,
+            opcode: self.state.memory.get(self.pc),
+            kind,
+        }
+    }
+
+    /* Something has been written to this address, so if it's part of a compiled instruction we check
+     * whether that instruction still matches what we compiled.  Putting the original words back puts
+     * it back in service */
+    fn invalidate(&mut self, address: usize) {
+        let program = self.program;
+        if !program.code.get(address).copied().unwrap_or(false) {
+            return;
+        }
+        // Compiled instructions never overlap, so it's the nearest one starting at or before the write
+        let Some(start) = (address.saturating_sub(3)..=address)
+            .rev()
+            .find(|&start| program.ops[start].is_some())
+        else {
+            return;
+        };
+        let size = Instruction::decode(program.image[start]).map_or(1, |i| i.size);
+        let changed = program.image[start..start + size]
+            .iter()
+            .enumerate()
+            .any(|(i, word)| self.state.memory.get(start + i) != *word);
+        if self.stale.is_empty() {
+            if !changed {
+                return;
+            }
+            self.stale = vec![false; program.ops.len()];
+        }
+        self.stale[start] = changed;
+    }
+
+    // Run a single instruction the slow way, straight out of memory
+    fn interpret(&mut self) -> Result<RunStatus, IntCodeError> {
+        self.interpreted += 1;
+        let instruction =
+            Instruction::decode(self.state.memory.get(self.pc)).map_err(|kind| self.error(kind))?;
+        let pc = self.pc;
+        let status = instruction.execute(self).map_err(|kind| {
+            self.pc = pc;
+            self.error(kind)
+        })?;
+        Ok(status)
+    }
+
+    // The same as IntCodeCPU::resume, run until we need input, have output or halt
+    pub fn resume(&mut self) -> Result<RunStatus, IntCodeError> {
+        if !self.running {
+            return Ok(RunStatus::Halted);
+        }
+        let program = self.program;
+        loop {
+            let Some(Some(op)) = program.ops.get(self.pc) else {
+                match self.interpret()? {
+                    RunStatus::Running => continue,
+                    status => return Ok(status),
+                }
+            };
+            if self.stale.get(self.pc).copied().unwrap_or(false) {
+                match self.interpret()? {
+                    RunStatus::Running => continue,
+                    status => return Ok(status),
+                }
+            }
+            match op(&mut self.state, &program.code) {
+                Step::Next(pc) => self.pc = pc,
+                Step::NeedsInput => return Ok(RunStatus::NeedsInput),
+                Step::Output(value, pc) => {
+                    self.pc = pc;
+                    return Ok(RunStatus::Output(value));
+                }
+                Step::Halt => {
+                    self.running = false;
+                    self.pc += 1;
+                    return Ok(RunStatus::Halted);
+                }
+                Step::Modified(address, pc) => {
+                    self.invalidate(address);
+                    self.pc = pc;
+                }
+                Step::Error(kind) => return Err(self.error(kind)),
+            }
+        }
+    }
+
+    pub fn run(&mut self) -> Result<(), IntCodeError> {
+        loop {
+            match self.resume()? {
+                RunStatus::Halted => return Ok(()),
+                RunStatus::NeedsInput => {
+                    return Err(self.error(IntCodeErrorKind::InputUnderflow));
+                }
+                RunStatus::Output(value) => self.output.push(value),
+                RunStatus::Running => {}
+            }
+        }
+    }
+}
+
+// So the instructions we can't run compiled can be interpreted against the same state
+impl Machine for CompiledCPU<'_> {
+    type Value = i64;
+    type Error = IntCodeErrorKind;
+
+    fn pc(&self) -> usize {
+        self.pc
+    }
+
+    fn set_pc(&mut self, pc: usize) {
+        self.pc = pc;
+    }
+
+    fn relative_base(&self) -> i64 {
+        self.state.relative_base
+    }
+
+    fn set_relative_base(&mut self, relative_base: i64) {
+        self.state.relative_base = relative_base;
+    }
+
+    fn crash(&self, kind: IntCodeErrorKind) -> IntCodeErrorKind {
+        kind
+    }
+
+    fn word(&self, address: usize) -> Option<i64> {
+        Some(self.state.memory.get(address))
+    }
+
+    fn immediate(&self, address: usize) -> i64 {
+        self.state.memory.get(address)
+    }
+
+    fn load(&mut self, address: usize) -> i64 {
+        self.state.memory.get(address)
+    }
+
+    fn store(&mut self, address: usize, value: i64) -> Result<(), IntCodeErrorKind> {
+        self.state.memory.set(address, value);
+        self.invalidate(address);
+        Ok(())
+    }
+
+    fn unknown_read(&self) -> Result<i64, IntCodeErrorKind> {
+        unreachable!("Every word of a compiled CPU's memory is known")
+    }
+
+    fn unknown_write(&self) -> IntCodeErrorKind {
+        unreachable!("Every word of a compiled CPU's memory is known")
+    }
+
+    fn constant(value: i64) -> i64 {
+        value
+    }
+
+    fn concrete(&self, value: i64) -> Result<i64, IntCodeErrorKind> {
+        Ok(value)
+    }
+
+    fn add(&self, a: i64, b: i64) -> Result<i64, IntCodeErrorKind> {
+        a.checked_add(b).ok_or(IntCodeErrorKind::Overflow)
+    }
+
+    fn mul(&self, a: i64, b: i64) -> Result<i64, IntCodeErrorKind> {
+        a.checked_mul(b).ok_or(IntCodeErrorKind::Overflow)
+    }
+
+    fn input(&mut self) -> Result<Option<i64>, IntCodeErrorKind> {
+        Ok(self.state.input.pop_front())
+    }
+
+    fn halt(&mut self) {
+        self.running = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::assembler::assemble;
+    use crate::intcode::compiler::*;
+    use crate::intcode::intcodecpu::IntCodeCPU;
+    use crate::intcode::parse_program;
+
+    /* Whatever the compiled program does, the interpreter should do exactly the same.  Returns how many
+     * instructions the compiled run had to interpret, and the interpreter, which ended up in the same
+     * state */
+    fn check(program: &[i64], input: Vec<i64>) -> (usize, IntCodeCPU) {
+        let compiled = CompiledProgram::compile(program);
+        let mut cpu = compiled.start(input.clone());
+        let mut interpreter = IntCodeCPU::new_with_io(program.to_vec(), input);
+        assert_eq!(cpu.run(), interpreter.run());
+        assert_eq!(cpu.output(), &interpreter.output[..]);
+        assert_eq!(cpu.memory().dense(), interpreter.memory.dense());
+        (cpu.interpreted(), interpreter)
+    }
+
+    #[test]
+    fn test_compiled_quine() {
+        let quine = parse_program("109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99");
+        assert_eq!(check(&quine, vec![]).0, 0);
+    }
+
+    #[test]
+    fn test_compiled_jumps() {
+        let program = parse_program(
+            "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99",
+        );
+        for input in [7, 8, 9] {
+            assert_eq!(check(&program, vec![input]).0, 0);
+        }
+    }
+
+    #[test]
+    fn test_compiled_skips_data_after_jumps() {
+        // The data looks like an ADD that would overlap the loop, but we never get to it by running on
+        let program = assemble(
+            "           JT #1, #loop
+                tmp:    DATA 1
+                n:      DATA 5
+                loop:   ADD [n], #-1 -> [n]
+                        LT [n], #3 -> [tmp]
+                        JT [n], #loop
+                        HLT",
+        )
+        .unwrap();
+        let compiled = CompiledProgram::compile(&program);
+        assert_eq!(compiled.compiled(), 5);
+        assert_eq!(check(&program, vec![]).0, 0);
+    }
+
+    #[test]
+    fn test_compiled_code_writes() {
+        // Day 2's example only writes over instructions it has already run, so they're never needed again
+        let (interpreted, _) = check(&parse_program("1,9,10,3,2,3,11,0,99,30,40,50"), vec![]);
+        assert_eq!(interpreted, 0);
+        // Patching an ADD into a MUL means interpreting it from then on, but nothing else
+        let program = assemble(
+            "   op:     ADD [acc], #3 -> [acc]
+                        ADD #1002, #0 -> [op]
+                        ADD [count], #1 -> [count]
+                        LT [count], #3 -> [tmp]
+                        JT [tmp], #op
+                        HLT
+                acc:    DATA 1
+                count:  DATA 0
+                tmp:    DATA 0",
+        )
+        .unwrap();
+        let (interpreted, cpu) = check(&program, vec![]);
+        assert_eq!(interpreted, 2);
+        assert_eq!(cpu.memory.get(20), 36);
+        // Putting the original words back means the compiled one is good to use again
+        let program = assemble(
+            "   op:     ADD [acc], #3 -> [acc]
+                        ADD [flip], #0 -> [op]
+                        MUL [flip], #-1 -> [flip]
+                        ADD [flip], #2003 -> [flip]
+                        ADD [count], #1 -> [count]
+                        LT [count], #4 -> [tmp]
+                        JT [tmp], #op
+                        HLT
+                acc:    DATA 1
+                flip:   DATA 1002
+                count:  DATA 0
+                tmp:    DATA 0",
+        )
+        .unwrap();
+        let (interpreted, cpu) = check(&program, vec![]);
+        assert_eq!(interpreted, 2);
+        assert_eq!(cpu.memory.get(28), 45);
+    }
+
+    #[test]
+    fn test_compiled_resume() {
+        let program = assemble(
+            "   loop:   IN -> [x]
+                        MUL [x], #2 -> [x]
+                        OUT [x]
+                        JT [x], #loop
+                        HLT
+                x:      DATA 0",
+        )
+        .unwrap();
+        let compiled = CompiledProgram::compile(&program);
+        assert_eq!(compiled.compiled(), 5);
+        let mut cpu = compiled.start(vec![]);
+        assert_eq!(cpu.resume(), Ok(RunStatus::NeedsInput));
+        cpu.push_input(21);
+        assert_eq!(cpu.resume(), Ok(RunStatus::Output(42)));
+        assert_eq!(cpu.resume(), Ok(RunStatus::NeedsInput));
+        cpu.push_input(0);
+        assert_eq!(cpu.resume(), Ok(RunStatus::Output(0)));
+        assert_eq!(cpu.resume(), Ok(RunStatus::Halted));
+        assert_eq!(cpu.interpreted(), 0);
+        // The same compiled program can be run again from scratch
+        assert_eq!(compiled.run(vec![1, 5, 0]), Ok(vec![2, 10, 0]));
+    }
+
+    #[test]
+    fn test_compiled_errors() {
+        // Jumping somewhere we never compiled means interpreting, which finds the bad opcode
+        let program = parse_program("1101,42,0,7,1105,1,7,0");
+        assert_eq!(check(&program, vec![]).0, 1);
+        let compiled = CompiledProgram::compile(&program);
+        assert_eq!(
+            compiled.run(vec![]).unwrap_err().kind,
+            IntCodeErrorKind::UnknownOpcode
+        );
+        // Overflowing is an error for the compiled ops too, and it's the same one the interpreter gives
+        for program in [
+            "1101,9223372036854775807,1,0,99",
+            "109,9223372036854775807,109,1,99",
+        ] {
+            let program = parse_program(program);
+            assert_eq!(check(&program, vec![]).0, 0);
+            assert_eq!(
+                CompiledProgram::compile(&program)
+                    .run(vec![])
+                    .unwrap_err()
+                    .kind,
+                IntCodeErrorKind::Overflow
+            );
+        }
+        let program = parse_program("3,0,99");
+        let compiled = CompiledProgram::compile(&program);
+        assert_eq!(
+            compiled.run(vec![]).unwrap_err().kind,
+            IntCodeErrorKind::InputUnderflow
+        );
+    }
+}
@@ -0,0 +1,366 @@
+use crate::intcode::disassembler::{Line, instruction_at};
+use crate::intcode::intcodecpu::{IntCodeCPU, IntCodeError, RunStatus};
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt::Write;
+
+// How many words a memory dump shows per row
+const DUMP_WIDTH: usize = 8;
+
+/* One instruction we've executed, captured just before it ran so the trace shows what the CPU
+ * actually saw, even if the code has since been overwritten */
+#[derive(Debug, Clone)]
+pub struct TraceEntry {
+    pub relative_base: i64,
+    pub line: Line,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StopReason {
+    Stepped,
+    Breakpoint(usize),
+    Watchpoint { address: usize, old: i64, new: i64 },
+    NeedsInput,
+    Halted,
+    Error(IntCodeError),
+}
+
+pub struct Debugger {
+    pub cpu: IntCodeCPU,
+    pub breakpoints: BTreeSet<usize>,
+    pub watchpoints: BTreeSet<usize>,
+    trace: VecDeque<TraceEntry>,
+    trace_length: usize,
+    // How much of cpu.output we've already shown, so we only print new values
+    outputs_shown: usize,
+}
+
+impl Debugger {
+    pub fn new(cpu: IntCodeCPU, trace_length: usize) -> Self {
+        Debugger {
+            cpu,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeSet::new(),
+            trace: VecDeque::with_capacity(trace_length),
+            trace_length,
+            outputs_shown: 0,
+        }
+    }
+
+    // The last n instructions executed, oldest first
+    pub fn trace(&self, n: usize) -> impl Iterator<Item = &TraceEntry> {
+        self.trace.iter().skip(self.trace.len().saturating_sub(n))
+    }
+
+    // Execute a single instruction, returning a reason if we should stop
+    fn step_one(&mut self) -> Option<StopReason> {
+        if !self.cpu.running {
+            return Some(StopReason::Halted);
+        }
+        let entry = TraceEntry {
+            relative_base: self.cpu.relative_base,
+            line: instruction_at(&self.cpu.memory, self.cpu.pc),
+        };
+        let watched: Vec<(usize, i64)> = self
+            .watchpoints
+            .iter()
+            .map(|a| (*a, self.cpu.memory.get(*a)))
+            .collect();
+        let status = self.cpu.execute();
+        // The CPU only hands outputs back, and we want to show them as they come
+        if let Ok(RunStatus::Output(value)) = status {
+            self.cpu.output.push(value);
+        }
+        // Waiting for input didn't actually execute anything, so it doesn't belong in the trace
+        if status != Ok(RunStatus::NeedsInput) {
+            if self.trace.len() == self.trace_length {
+                self.trace.pop_front();
+            }
+            if self.trace_length > 0 {
+                self.trace.push_back(entry);
+            }
+        }
+        match status {
+            Err(error) => return Some(StopReason::Error(error)),
+            Ok(RunStatus::NeedsInput) => return Some(StopReason::NeedsInput),
+            Ok(RunStatus::Halted) => return Some(StopReason::Halted),
+            Ok(_) => {}
+        }
+        watched.into_iter().find_map(|(address, old)| {
+            let new = self.cpu.memory.get(address);
+            (old != new).then_some(StopReason::Watchpoint { address, old, new })
+        })
+    }
+
+    pub fn step(&mut self) -> StopReason {
+        self.step_one().unwrap_or(StopReason::Stepped)
+    }
+
+    // Run until something stops us, we always execute at least one instruction so we can continue off a breakpoint
+    pub fn cont(&mut self) -> StopReason {
+        loop {
+            if let Some(reason) = self.step_one() {
+                return reason;
+            }
+            if self.breakpoints.contains(&self.cpu.pc) {
+                return StopReason::Breakpoint(self.cpu.pc);
+            }
+        }
+    }
+
+    pub fn registers(&self) -> String {
+        format!(
+            "pc: {}  rb: {}  running: {}  input: {:?}  output: {} values",
+            self.cpu.pc,
+            self.cpu.relative_base,
+            self.cpu.running,
+            self.cpu.input,
+            self.cpu.output.len()
+        )
+    }
+
+    pub fn dump(&self, address: usize, count: usize) -> String {
+        let mut text = String::new();
+        for row in (address..address + count).step_by(DUMP_WIDTH) {
+            let end = (row + DUMP_WIDTH).min(address + count);
+            let words: Vec<String> = (row..end)
+                .map(|a| self.cpu.memory.get(a).to_string())
+                .collect();
+            writeln!(text, "{:04}: {}", row, words.join(" ")).unwrap();
+        }
+        text
+    }
+
+    // Disassemble a few instructions from an address, marking where the pc is and any breakpoints
+    pub fn list(&self, address: usize, count: usize) -> String {
+        let mut text = String::new();
+        let mut address = address;
+        for _ in 0..count {
+            let line = instruction_at(&self.cpu.memory, address);
+            let marker = match (address == self.cpu.pc, self.breakpoints.contains(&address)) {
+                (true, _) => "=>",
+                (false, true) => " *",
+                (false, false) => "  ",
+            };
+            writeln!(text, "{} {:04}: {}", marker, address, line.text()).unwrap();
+            address += line.words.len();
+        }
+        text
+    }
+
+    pub fn format_trace(&self, n: usize) -> String {
+        let mut text = String::new();
+        for entry in self.trace(n) {
+            writeln!(
+                text,
+                "{:04}: {:<32} rb={}",
+                entry.line.address,
+                entry.line.text(),
+                entry.relative_base
+            )
+            .unwrap();
+        }
+        text
+    }
+
+    fn describe(&mut self, reason: &StopReason) -> String {
+        let mut text = String::new();
+        for value in &self.cpu.output[self.outputs_shown..] {
+            writeln!(text, "output: {}", value).unwrap();
+        }
+        self.outputs_shown = self.cpu.output.len();
+        match reason {
+            StopReason::Stepped => {}
+            StopReason::Breakpoint(address) => writeln!(text, "breakpoint at {}", address).unwrap(),
+            StopReason::Watchpoint { address, old, new } => {
+                writeln!(text, "watchpoint [{}] changed {} -> {}", address, old, new).unwrap()
+            }
+            StopReason::NeedsInput => writeln!(text, "waiting for input").unwrap(),
+            // Stopping for good is usually when we want to know how we got here
+            StopReason::Halted => {
+                writeln!(text, "halted, last instructions:").unwrap();
+                text.push_str(&self.format_trace(self.trace_length));
+            }
+            StopReason::Error(error) => {
+                writeln!(text, "error: {}, last instructions:", error).unwrap();
+                text.push_str(&self.format_trace(self.trace_length));
+            }
+        }
+        if self.cpu.running {
+            text.push_str(&self.list(self.cpu.pc, 1));
+        }
+        text
+    }
+
+    /* Run one line of debugger command, returning what to show the user, or None if they want to quit.
+     * Addresses and counts are plain decimal, like everywhere else in intcode */
+    pub fn command(&mut self, line: &str) -> Option<String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((command, args)) = words.split_first() else {
+            return Some(String::new());
+        };
+        let numbers: Result<Vec<i64>, _> = args.iter().map(|a| a.parse::<i64>()).collect();
+        let Ok(numbers) = numbers else {
+            return Some(format!("can't parse arguments: {}\n", args.join(" ")));
+        };
+        let arg = |n: usize, default: usize| {
+            numbers
+                .get(n)
+                .map(|v| usize::try_from(*v).unwrap_or(default))
+                .unwrap_or(default)
+        };
+        Some(match *command {
+            "s" | "step" => {
+                let mut reason = StopReason::Stepped;
+                for _ in 0..arg(0, 1) {
+                    reason = self.step();
+                    if reason != StopReason::Stepped {
+                        break;
+                    }
+                }
+                self.describe(&reason)
+            }
+            "c" | "continue" => {
+                let reason = self.cont();
+                self.describe(&reason)
+            }
+            "b" | "break" if !numbers.is_empty() => {
+                self.breakpoints.insert(arg(0, 0));
+                format!("breakpoints: {:?}\n", self.breakpoints)
+            }
+            "d" | "delete" if !numbers.is_empty() => {
+                self.breakpoints.remove(&arg(0, 0));
+                format!("breakpoints: {:?}\n", self.breakpoints)
+            }
+            "w" | "watch" if !numbers.is_empty() => {
+                self.watchpoints.insert(arg(0, 0));
+                format!("watchpoints: {:?}\n", self.watchpoints)
+            }
+            "uw" | "unwatch" if !numbers.is_empty() => {
+                self.watchpoints.remove(&arg(0, 0));
+                format!("watchpoints: {:?}\n", self.watchpoints)
+            }
+            "x" | "mem" if !numbers.is_empty() => self.dump(arg(0, 0), arg(1, 16)),
+            "r" | "regs" => format!("{}\n", self.registers()),
+            "t" | "trace" => self.format_trace(arg(0, self.trace_length)),
+            "l" | "list" => self.list(arg(0, self.cpu.pc), arg(1, 10)),
+            "i" | "input" => {
+                for value in &numbers {
+                    self.cpu.push_input(*value);
+                }
+                format!("input: {:?}\n", self.cpu.input)
+            }
+            "q" | "quit" => return None,
+            _ => "commands: step [n], continue, break <addr>, delete <addr>, watch <addr>, unwatch <addr>,
+          mem <addr> [count], regs, trace [n], list [addr] [count], input <values>, quit\n"
+                .to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::assembler::assemble;
+    use crate::intcode::debugger::*;
+    use crate::intcode::intcodecpu::IntCodeErrorKind;
+
+    fn countdown() -> IntCodeCPU {
+        IntCodeCPU::new(
+            assemble(
+                "loop:   OUT [counter]
+                         ADD [counter], #-1 -> [counter]
+                         JT [counter], #loop
+                         HLT
+                counter: DATA 3",
+            )
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_debugger_step() {
+        let mut debugger = Debugger::new(countdown(), 10);
+        assert_eq!(debugger.step(), StopReason::Stepped);
+        assert_eq!(debugger.cpu.pc, 2);
+        assert_eq!(debugger.cpu.output, vec![3]);
+        assert_eq!(debugger.trace(10).count(), 1);
+    }
+
+    #[test]
+    fn test_debugger_breakpoint() {
+        let mut debugger = Debugger::new(countdown(), 10);
+        debugger.breakpoints.insert(6);
+        assert_eq!(debugger.cont(), StopReason::Breakpoint(6));
+        assert_eq!(debugger.cpu.memory[10], 2);
+        // Continuing moves off the breakpoint and round the loop back to it
+        assert_eq!(debugger.cont(), StopReason::Breakpoint(6));
+        assert_eq!(debugger.cpu.memory[10], 1);
+        debugger.breakpoints.clear();
+        assert_eq!(debugger.cont(), StopReason::Halted);
+        assert_eq!(debugger.cpu.output, vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_debugger_watchpoint() {
+        let mut debugger = Debugger::new(countdown(), 10);
+        debugger.watchpoints.insert(10);
+        assert_eq!(
+            debugger.cont(),
+            StopReason::Watchpoint {
+                address: 10,
+                old: 3,
+                new: 2
+            }
+        );
+        assert_eq!(debugger.cpu.pc, 6);
+    }
+
+    #[test]
+    fn test_debugger_trace_is_bounded() {
+        let mut debugger = Debugger::new(countdown(), 4);
+        assert_eq!(debugger.cont(), StopReason::Halted);
+        let trace: Vec<String> = debugger.trace(10).map(|t| t.line.text()).collect();
+        assert_eq!(
+            trace,
+            vec!["OUT [10]", "ADD [10], #-1 -> [10]", "JT [10], #0", "HLT"]
+        );
+        assert_eq!(debugger.trace(1).count(), 1);
+    }
+
+    #[test]
+    fn test_debugger_error() {
+        let mut debugger = Debugger::new(IntCodeCPU::new(vec![1101, 1, 1, 5, 104, 0, 42]), 10);
+        match debugger.cont() {
+            StopReason::Error(error) => {
+                assert_eq!(error.pc, 6);
+                assert_eq!(error.kind, IntCodeErrorKind::UnknownOpcode);
+            }
+            reason => panic!("Expected an error, got {:?}", reason),
+        }
+    }
+
+    #[test]
+    fn test_debugger_commands() {
+        let mut debugger = Debugger::new(IntCodeCPU::new(vec![3, 9, 4, 9, 99]), 10);
+        assert_eq!(
+            debugger.command("s").unwrap(),
+            "waiting for input\n=> 0000: IN -> [9]\n"
+        );
+        assert_eq!(debugger.command("i 42").unwrap(), "input: [42]\n");
+        assert_eq!(debugger.command("b 4").unwrap(), "breakpoints: {4}\n");
+        assert_eq!(
+            debugger.command("c").unwrap(),
+            "output: 42\nbreakpoint at 4\n=> 0004: HLT\n"
+        );
+        assert_eq!(debugger.command("x 8 2").unwrap(), "0008: 0 42\n");
+        assert_eq!(
+            debugger.command("r").unwrap(),
+            "pc: 4  rb: 0  running: true  input: []  output: 1 values\n"
+        );
+        assert_eq!(
+            debugger.command("t 1").unwrap(),
+            format!("0002: {:<32} rb=0\n", "OUT [9]")
+        );
+        assert!(debugger.command("c").unwrap().starts_with("halted"));
+        assert_eq!(debugger.command("q"), None);
+    }
+}
@@ -0,0 +1,491 @@
+use crate::intcode::intcodecpu::{
+    IntCodeCPU, IntCodeErrorKind, Modes, RunStatus, relative_address, to_address,
+};
+use std::sync::Arc;
+
+/* The execution core.  The original one built a Vec of modes for every instruction it ran, which was
+ * most of the time spent on something like day 2 part 2.  An Instruction is the same thing decoded into a
+ * small Copy value, and the cache keeps the decode for each address so loops only decode once */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Operation {
+    Add,
+    Mul,
+    Input,
+    Output,
+    JumpIfTrue,
+    JumpIfFalse,
+    LessThan,
+    Equals,
+    AdjustRelativeBase,
+    Halt,
+}
+
+impl Operation {
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Operation::Add => "ADD",
+            Operation::Mul => "MUL",
+            Operation::Input => "IN",
+            Operation::Output => "OUT",
+            Operation::JumpIfTrue => "JT",
+            Operation::JumpIfFalse => "JF",
+            Operation::LessThan => "LT",
+            Operation::Equals => "EQ",
+            Operation::AdjustRelativeBase => "ARB",
+            Operation::Halt => "HLT",
+        }
+    }
+
+    // Which parameter (1 based, like the offsets) is written to, if any
+    pub fn write_parameter(&self) -> Option<usize> {
+        match self {
+            Operation::Add | Operation::Mul | Operation::LessThan | Operation::Equals => Some(3),
+            Operation::Input => Some(1),
+            _ => None,
+        }
+    }
+}
+
+/* What an instruction runs on.  IntCodeCPU is the real thing, and the symbolic CPU keeps polynomials
+ * in its cells rather than numbers, but otherwise they work the same way, so the instructions are only
+ * written once, below.  Anything a machine can't cope with comes back as its own kind of error */
+pub(crate) trait Machine {
+    type Value;
+    type Error;
+
+    fn pc(&self) -> usize;
+    fn set_pc(&mut self, pc: usize);
+    fn relative_base(&self) -> i64;
+    fn set_relative_base(&mut self, relative_base: i64);
+    fn crash(&self, kind: IntCodeErrorKind) -> Self::Error;
+
+    // A word as a plain number, which we need for using it as an address.  None if we can't know it
+    fn word(&self, address: usize) -> Option<i64>;
+    // Parameters in immediate mode, which aren't counted as data reads
+    fn immediate(&self, address: usize) -> Self::Value;
+    fn load(&mut self, address: usize) -> Self::Value;
+    fn store(&mut self, address: usize, value: Self::Value) -> Result<(), Self::Error>;
+    // What to do when a read or a write goes through an address word() couldn't tell us
+    fn unknown_read(&self) -> Result<Self::Value, Self::Error>;
+    fn unknown_write(&self) -> Self::Error;
+
+    fn constant(value: i64) -> Self::Value;
+    // Comparisons, jumps and the relative base need an actual number to work with
+    fn concrete(&self, value: Self::Value) -> Result<i64, Self::Error>;
+    fn add(&self, a: Self::Value, b: Self::Value) -> Result<Self::Value, Self::Error>;
+    fn mul(&self, a: Self::Value, b: Self::Value) -> Result<Self::Value, Self::Error>;
+    // None if there isn't any input yet
+    fn input(&mut self) -> Result<Option<Self::Value>, Self::Error>;
+    fn halt(&mut self);
+}
+
+// All of these are on the hot path, so they need to be inlined into the instructions
+impl Machine for IntCodeCPU {
+    type Value = i64;
+    type Error = IntCodeErrorKind;
+
+    #[inline]
+    fn pc(&self) -> usize {
+        self.pc
+    }
+
+    #[inline]
+    fn set_pc(&mut self, pc: usize) {
+        self.pc = pc;
+    }
+
+    #[inline]
+    fn relative_base(&self) -> i64 {
+        self.relative_base
+    }
+
+    #[inline]
+    fn set_relative_base(&mut self, relative_base: i64) {
+        self.relative_base = relative_base;
+    }
+
+    #[inline]
+    fn crash(&self, kind: IntCodeErrorKind) -> IntCodeErrorKind {
+        kind
+    }
+
+    #[inline]
+    fn word(&self, address: usize) -> Option<i64> {
+        Some(self.memory.get(address))
+    }
+
+    #[inline]
+    fn immediate(&self, address: usize) -> i64 {
+        self.memory.get(address)
+    }
+
+    // Data reads and writes go through these so the profiler, if it's on, can count them
+    #[inline]
+    fn load(&mut self, address: usize) -> i64 {
+        if let Some(profile) = &mut self.profile {
+            profile.read(address);
+        }
+        self.memory.get(address)
+    }
+
+    #[inline]
+    fn store(&mut self, address: usize, value: i64) -> Result<(), IntCodeErrorKind> {
+        if let Some(profile) = &mut self.profile {
+            profile.write(address);
+        }
+        self.memory.set(address, value);
+        Ok(())
+    }
+
+    #[inline]
+    fn unknown_read(&self) -> Result<i64, IntCodeErrorKind> {
+        unreachable!("Every word of a real CPU's memory is known")
+    }
+
+    #[inline]
+    fn unknown_write(&self) -> IntCodeErrorKind {
+        unreachable!("Every word of a real CPU's memory is known")
+    }
+
+    #[inline]
+    fn constant(value: i64) -> i64 {
+        value
+    }
+
+    #[inline]
+    fn concrete(&self, value: i64) -> Result<i64, IntCodeErrorKind> {
+        Ok(value)
+    }
+
+    #[inline]
+    fn add(&self, a: i64, b: i64) -> Result<i64, IntCodeErrorKind> {
+        a.checked_add(b).ok_or(IntCodeErrorKind::Overflow)
+    }
+
+    #[inline]
+    fn mul(&self, a: i64, b: i64) -> Result<i64, IntCodeErrorKind> {
+        a.checked_mul(b).ok_or(IntCodeErrorKind::Overflow)
+    }
+
+    #[inline]
+    fn input(&mut self) -> Result<Option<i64>, IntCodeErrorKind> {
+        Ok(self.input.pop_front())
+    }
+
+    #[inline]
+    fn halt(&mut self) {
+        self.running = false;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
+    pub operation: Operation,
+    // Ops with fewer than three parameters just leave the rest as Position
+    pub modes: [Modes; 3],
+    pub size: usize,
+}
+
+impl Instruction {
+    pub fn decode(opcode: i64) -> Result<Self, IntCodeErrorKind> {
+        let (operation, size) = match opcode % 100 {
+            1 => (Operation::Add, 4),
+            2 => (Operation::Mul, 4),
+            3 => (Operation::Input, 2),
+            4 => (Operation::Output, 2),
+            5 => (Operation::JumpIfTrue, 3),
+            6 => (Operation::JumpIfFalse, 3),
+            7 => (Operation::LessThan, 4),
+            8 => (Operation::Equals, 4),
+            9 => (Operation::AdjustRelativeBase, 2),
+            99 => (Operation::Halt, 1),
+            _ => return Err(IntCodeErrorKind::UnknownOpcode),
+        };
+        let mut modes = [Modes::Position; 3];
+        let mut flags = opcode / 100;
+        for mode in modes.iter_mut().take(size - 1) {
+            *mode = Modes::from(flags % 10)?;
+            flags /= 10;
+        }
+        Ok(Instruction {
+            operation,
+            modes,
+            size,
+        })
+    }
+
+    fn parameter<M: Machine>(&self, machine: &mut M, offset: usize) -> Result<M::Value, M::Error> {
+        let address = machine.pc() + offset;
+        let base = match self.modes[offset - 1] {
+            Modes::Position => 0,
+            Modes::Immediate => return Ok(machine.immediate(address)),
+            Modes::Relative => machine.relative_base(),
+        };
+        match machine.word(address) {
+            Some(value) => {
+                let address = relative_address(base, value).map_err(|kind| machine.crash(kind))?;
+                Ok(machine.load(address))
+            }
+            None => machine.unknown_read(),
+        }
+    }
+
+    fn address<M: Machine>(&self, machine: &M, offset: usize) -> Result<usize, M::Error> {
+        let base = match self.modes[offset - 1] {
+            Modes::Position => 0,
+            Modes::Immediate => return Err(machine.crash(IntCodeErrorKind::ImmediateWrite)),
+            Modes::Relative => machine.relative_base(),
+        };
+        let value = machine
+            .word(machine.pc() + offset)
+            .ok_or_else(|| machine.unknown_write())?;
+        relative_address(base, value).map_err(|kind| machine.crash(kind))
+    }
+
+    fn concrete<M: Machine>(&self, machine: &mut M, offset: usize) -> Result<i64, M::Error> {
+        let value = self.parameter(machine, offset)?;
+        machine.concrete(value)
+    }
+
+    // The three parameter ops all work out a value from two parameters and write it to the third
+    fn binary<M: Machine>(
+        &self,
+        machine: &mut M,
+        f: impl Fn(&M, M::Value, M::Value) -> Result<M::Value, M::Error>,
+    ) -> Result<(), M::Error> {
+        let target = self.address(machine, 3)?;
+        let a = self.parameter(machine, 1)?;
+        let b = self.parameter(machine, 2)?;
+        let value = f(machine, a, b)?;
+        machine.store(target, value)?;
+        machine.set_pc(machine.pc() + self.size);
+        Ok(())
+    }
+
+    fn jump<M: Machine>(&self, machine: &mut M, when: bool) -> Result<(), M::Error> {
+        if (self.concrete(machine, 1)? != 0) == when {
+            let target = self.concrete(machine, 2)?;
+            machine.set_pc(to_address(target).map_err(|kind| machine.crash(kind))?);
+        } else {
+            machine.set_pc(machine.pc() + self.size);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn execute<M: Machine>(
+        &self,
+        machine: &mut M,
+    ) -> Result<RunStatus<M::Value>, M::Error> {
+        match self.operation {
+            Operation::Add => self.binary(machine, |m, a, b| m.add(a, b))?,
+            Operation::Mul => self.binary(machine, |m, a, b| m.mul(a, b))?,
+            Operation::LessThan => self.binary(machine, |m, a, b| {
+                Ok(M::constant((m.concrete(a)? < m.concrete(b)?) as i64))
+            })?,
+            Operation::Equals => self.binary(machine, |m, a, b| {
+                Ok(M::constant((m.concrete(a)? == m.concrete(b)?) as i64))
+            })?,
+            Operation::Input => {
+                let target = self.address(machine, 1)?;
+                // Leave the pc where it is, so once some input arrives we retry this instruction
+                let Some(value) = machine.input()? else {
+                    return Ok(RunStatus::NeedsInput);
+                };
+                machine.store(target, value)?;
+                machine.set_pc(machine.pc() + self.size);
+            }
+            Operation::Output => {
+                let value = self.parameter(machine, 1)?;
+                machine.set_pc(machine.pc() + self.size);
+                return Ok(RunStatus::Output(value));
+            }
+            Operation::JumpIfTrue => self.jump(machine, true)?,
+            Operation::JumpIfFalse => self.jump(machine, false)?,
+            Operation::AdjustRelativeBase => {
+                let adjustment = self.concrete(machine, 1)?;
+                let relative_base = machine
+                    .relative_base()
+                    .checked_add(adjustment)
+                    .ok_or_else(|| machine.crash(IntCodeErrorKind::Overflow))?;
+                machine.set_relative_base(relative_base);
+                machine.set_pc(machine.pc() + self.size);
+            }
+            Operation::Halt => {
+                machine.halt();
+                machine.set_pc(machine.pc() + self.size);
+                return Ok(RunStatus::Halted);
+            }
+        }
+        Ok(RunStatus::Running)
+    }
+}
+
+type Decoded = Vec<Option<(i64, Instruction)>>;
+
+#[derive(Debug, Clone)]
+enum Entries {
+    Owned(Decoded),
+    Shared(Arc<Decoded>),
+}
+
+/* Decoded instructions by address.  Each entry remembers the word it was decoded from, and we only
+ * trust it if memory still holds that word, so a program that writes over its own code (day 5 does)
+ * or a caller poking at cpu.memory just gets a fresh decode rather than running something stale.
+ * Like Memory, share() puts the entries behind an Arc so snapshots don't copy them, and the first
+ * new decode after that takes a copy (or takes them back, if nobody else is still holding them) */
+#[derive(Debug, Clone)]
+pub struct DecodeCache {
+    entries: Entries,
+}
+
+impl Default for DecodeCache {
+    fn default() -> Self {
+        DecodeCache {
+            entries: Entries::Owned(vec![]),
+        }
+    }
+}
+
+/* Only addresses inside dense memory (limit is its length) are cached, so the cache is never bigger
+ * than memory is.  Running code out in sparse memory, or jumping somewhere silly, just decodes every
+ * time rather than growing the cache out to that address */
+impl DecodeCache {
+    pub fn share(&mut self) {
+        if let Entries::Owned(entries) = &mut self.entries {
+            self.entries = Entries::Shared(Arc::new(std::mem::take(entries)));
+        }
+    }
+
+    pub fn is_shared(&self) -> bool {
+        matches!(self.entries, Entries::Shared(_))
+    }
+
+    fn entries(&self) -> &Decoded {
+        match &self.entries {
+            Entries::Owned(entries) => entries,
+            Entries::Shared(entries) => entries,
+        }
+    }
+
+    fn entries_mut(&mut self) -> &mut Decoded {
+        if let Entries::Shared(entries) = &self.entries {
+            let entries = Arc::clone(entries);
+            self.entries = Entries::Owned(vec![]);
+            self.entries = Entries::Owned(Arc::unwrap_or_clone(entries));
+        }
+        match &mut self.entries {
+            Entries::Owned(entries) => entries,
+            Entries::Shared(_) => unreachable!("The cache was just made unique"),
+        }
+    }
+
+    pub fn decode(
+        &mut self,
+        address: usize,
+        word: i64,
+        limit: usize,
+    ) -> Result<Instruction, IntCodeErrorKind> {
+        if let Some(Some((cached, instruction))) = self.entries().get(address)
+            && *cached == word
+        {
+            return Ok(*instruction);
+        }
+        self.insert(address, word, limit)
+    }
+
+    // Loops hit the cache almost every time, so keep this out of the way of the lookup
+    #[inline(never)]
+    fn insert(
+        &mut self,
+        address: usize,
+        word: i64,
+        limit: usize,
+    ) -> Result<Instruction, IntCodeErrorKind> {
+        let instruction = Instruction::decode(word)?;
+        if address < limit {
+            let entries = self.entries_mut();
+            if address >= entries.len() {
+                entries.resize(address + 1, None);
+            }
+            entries[address] = Some((word, instruction));
+        }
+        Ok(instruction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::assembler::assemble;
+    use crate::intcode::decode::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(
+            Instruction::decode(1002),
+            Ok(Instruction {
+                operation: Operation::Mul,
+                modes: [Modes::Position, Modes::Immediate, Modes::Position],
+                size: 4
+            })
+        );
+        assert_eq!(
+            Instruction::decode(209).unwrap().modes,
+            [Modes::Relative, Modes::Position, Modes::Position]
+        );
+        assert_eq!(Instruction::decode(99).unwrap().operation, Operation::Halt);
+        assert_eq!(
+            Instruction::decode(42),
+            Err(IntCodeErrorKind::UnknownOpcode)
+        );
+        assert_eq!(
+            Instruction::decode(301),
+            Err(IntCodeErrorKind::UnknownMode(3))
+        );
+    }
+
+    #[test]
+    fn test_decode_cache_notices_new_code() {
+        let mut cache = DecodeCache::default();
+        assert_eq!(cache.decode(4, 1, 10).unwrap().operation, Operation::Add);
+        assert_eq!(cache.decode(4, 1, 10).unwrap().operation, Operation::Add);
+        assert_eq!(cache.decode(4, 2, 10).unwrap().operation, Operation::Mul);
+        assert_eq!(
+            cache.decode(4, 7, 10).unwrap().operation,
+            Operation::LessThan
+        );
+    }
+
+    #[test]
+    fn test_decode_cache_stays_inside_memory() {
+        let mut cache = DecodeCache::default();
+        cache.decode(4, 1, 10).unwrap();
+        assert_eq!(cache.entries().len(), 5);
+        // Way out past the end of memory still decodes, but doesn't grow the cache to get there
+        assert_eq!(
+            cache.decode(500_000, 2, 10).unwrap().operation,
+            Operation::Mul
+        );
+        assert_eq!(cache.entries().len(), 5);
+    }
+
+    #[test]
+    fn test_self_modifying_code() {
+        // Goes round twice, turning its own ADD into a MUL on the way, so a stale decode would give 7
+        let mut cpu = IntCodeCPU::new(
+            assemble(
+                "   op:     ADD [acc], #3 -> [acc]
+                            ADD #1002, #0 -> [op]
+                            ADD [count], #1 -> [count]
+                            LT [count], #2 -> [tmp]
+                            JT [tmp], #op
+                            HLT
+                    acc:    DATA 1
+                    count:  DATA 0
+                    tmp:    DATA 0",
+            )
+            .unwrap(),
+        );
+        cpu.run().unwrap();
+        assert_eq!(cpu.memory[20], 12);
+    }
+}
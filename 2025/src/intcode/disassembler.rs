@@ -0,0 +1,413 @@
+use crate::intcode::decode::{Instruction, Operation};
+use crate::intcode::intcodecpu::Modes;
+use crate::intcode::memory::Memory;
+use std::collections::{BTreeSet, HashSet, VecDeque};
+use std::fmt;
+
+// How many words of data we put on a single DATA line
+const DATA_PER_LINE: usize = 8;
+// The longest instruction is an opcode and three parameters
+const MAX_INSTRUCTION_SIZE: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    Position(i64),
+    Immediate(i64),
+    Relative(i64),
+    // An immediate value that we know is the address of some code, so we print it as a label
+    Label(usize),
+}
+
+impl Operand {
+    fn new(mode: Modes, value: i64) -> Self {
+        match mode {
+            Modes::Position => Operand::Position(value),
+            Modes::Immediate => Operand::Immediate(value),
+            Modes::Relative => Operand::Relative(value),
+        }
+    }
+}
+
+pub fn label_name(address: usize) -> String {
+    format!("L{:04}", address)
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Position(value) => write!(f, "[{}]", value),
+            Operand::Immediate(value) => write!(f, "#{}", value),
+            Operand::Relative(value) if *value < 0 => write!(f, "[rb{}]", value),
+            Operand::Relative(value) => write!(f, "[rb+{}]", value),
+            Operand::Label(address) => write!(f, "#{}", label_name(*address)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LineKind {
+    Instruction {
+        mnemonic: &'static str,
+        operands: Vec<Operand>,
+        target: Option<Operand>,
+    },
+    Data,
+}
+
+/* A single line of the listing, either an instruction or a run of data words.  We keep the raw words
+ * so the listing can show exactly what was in memory */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub address: usize,
+    pub words: Vec<i64>,
+    pub kind: LineKind,
+}
+
+impl Line {
+    pub fn text(&self) -> String {
+        match &self.kind {
+            LineKind::Instruction {
+                mnemonic,
+                operands,
+                target,
+            } => {
+                let mut text = mnemonic.to_string();
+                if !operands.is_empty() {
+                    text.push(' ');
+                    text.push_str(
+                        &operands
+                            .iter()
+                            .map(|o| o.to_string())
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                    );
+                }
+                if let Some(target) = target {
+                    text.push_str(&format!(" -> {}", target));
+                }
+                text
+            }
+            LineKind::Data => format!(
+                "DATA {}",
+                self.words
+                    .iter()
+                    .map(|w| w.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+pub struct Listing {
+    pub lines: Vec<Line>,
+    pub labels: BTreeSet<usize>,
+}
+
+/* The listing is valid assembly, the address and raw words go in a comment on the end of each line,
+ * and labels get a line to themselves */
+impl fmt::Display for Listing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            if self.labels.contains(&line.address) {
+                writeln!(f, "{}:", label_name(line.address))?;
+            }
+            let words = line
+                .words
+                .iter()
+                .map(|w| w.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            writeln!(
+                f,
+                "    {:<40} ; {:04}: {}",
+                line.text(),
+                line.address,
+                words
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Decode the instruction at an address, if it's something the CPU could actually execute
+fn decode(program: &[i64], address: usize) -> Option<Instruction> {
+    let op = Instruction::decode(*program.get(address)?).ok()?;
+    if address + op.size > program.len() {
+        return None;
+    }
+    if let Some(write) = op.operation.write_parameter()
+        && op.modes[write - 1] == Modes::Immediate
+    {
+        return None;
+    }
+    Some(op)
+}
+
+// Where execution can go after this instruction, and which of those are jumps that deserve a label
+fn successors(program: &[i64], address: usize, op: &Instruction) -> (Vec<usize>, Vec<usize>) {
+    let size = op.size;
+    let param = |n: usize| (op.modes[n - 1], program[address + n]);
+    match op.operation {
+        Operation::Halt => (vec![], vec![]),
+        Operation::JumpIfTrue | Operation::JumpIfFalse => {
+            let jump_when = op.operation == Operation::JumpIfTrue;
+            let mut next = vec![];
+            let mut jumps = vec![];
+            // An immediate condition means the jump is either always or never taken
+            let (always, never) = match param(1) {
+                (Modes::Immediate, value) => ((value != 0) == jump_when, (value != 0) != jump_when),
+                _ => (false, false),
+            };
+            if !always {
+                next.push(address + size);
+            }
+            if !never
+                && let (Modes::Immediate, target) = param(2)
+                && let Ok(target) = usize::try_from(target)
+            {
+                next.push(target);
+                jumps.push(target);
+            }
+            (next, jumps)
+        }
+        Operation::Add => {
+            /* Calls push their return address with something like ADD #ret, #0 -> [rb+0] before jumping,
+             * and the return is an indirect jump we can't follow, so treat that constant as code too */
+            let mut next = vec![address + size];
+            let mut jumps = vec![];
+            if let ((Modes::Immediate, a), (Modes::Immediate, b)) = (param(1), param(2))
+                && (a == 0 || b == 0)
+                && let Ok(value) = usize::try_from(a + b)
+                && value > address
+                && decode(program, value).is_some()
+            {
+                next.push(value);
+                jumps.push(value);
+            }
+            (next, jumps)
+        }
+        _ => (vec![address + size], vec![]),
+    }
+}
+
+/* Work out which addresses are code by following the control flow from the start of the program.
+ * Anything we can't reach is treated as data.  That includes code the program patches before running
+ * it, like the opcode at 6 in day 5, so disassembling memory after a run gives a truer picture.
+ * Returns the start of each instruction and the addresses that are the targets of jumps */
+pub(crate) fn trace(program: &[i64]) -> (BTreeSet<usize>, BTreeSet<usize>) {
+    let mut starts = BTreeSet::new();
+    let mut labels = BTreeSet::new();
+    // Every address that's part of an instruction, so we don't decode over the middle of another one
+    let mut claimed = HashSet::new();
+    let mut queue = VecDeque::from([0]);
+    while let Some(address) = queue.pop_front() {
+        if claimed.contains(&address) {
+            continue;
+        }
+        let Some(op) = decode(program, address) else {
+            continue;
+        };
+        let size = op.size;
+        if (address..address + size).any(|a| claimed.contains(&a)) {
+            continue;
+        }
+        claimed.extend(address..address + size);
+        starts.insert(address);
+        let (next, jumps) = successors(program, address, &op);
+        labels.extend(jumps);
+        queue.extend(next);
+    }
+    // Only keep labels that ended up pointing at the start of an instruction
+    labels.retain(|a| starts.contains(a));
+    (starts, labels)
+}
+
+fn instruction_line(
+    op: &Instruction,
+    address: usize,
+    words: Vec<i64>,
+    labels: &BTreeSet<usize>,
+) -> Line {
+    let mut operands: Vec<Operand> = op.modes[..op.size - 1]
+        .iter()
+        .zip(&words[1..])
+        .map(|(mode, value)| Operand::new(*mode, *value))
+        .collect();
+    // Immediate jump targets and return addresses read better as labels, jump conditions never do
+    let labelled = match op.operation {
+        Operation::JumpIfTrue | Operation::JumpIfFalse => 1..2,
+        Operation::Add => 0..2,
+        _ => 0..0,
+    };
+    for operand in &mut operands[labelled] {
+        if let Operand::Immediate(value) = *operand
+            && let Ok(target) = usize::try_from(value)
+            && labels.contains(&target)
+        {
+            *operand = Operand::Label(target);
+        }
+    }
+    let target = op.operation.write_parameter().map(|n| operands.remove(n - 1));
+    Line {
+        address,
+        words,
+        kind: LineKind::Instruction {
+            mnemonic: op.operation.mnemonic(),
+            operands,
+            target,
+        },
+    }
+}
+
+/* Decode just the one instruction at an address in a running CPU's memory, which is what the debugger
+ * wants for showing where it's stopped.  If it doesn't decode we get a single word of data */
+pub fn instruction_at(memory: &Memory, address: usize) -> Line {
+    let words: Vec<i64> = (address..address + MAX_INSTRUCTION_SIZE)
+        .map(|a| memory.get(a))
+        .collect();
+    match decode(&words, 0) {
+        Some(op) => {
+            let size = op.size;
+            instruction_line(&op, address, words[..size].to_vec(), &BTreeSet::new())
+        }
+        None => Line {
+            address,
+            words: vec![words[0]],
+            kind: LineKind::Data,
+        },
+    }
+}
+
+pub fn disassemble(program: &[i64]) -> Listing {
+    let (starts, labels) = trace(program);
+    let mut lines = vec![];
+    let mut address = 0;
+    while address < program.len() {
+        if starts.contains(&address) {
+            let op = decode(program, address).expect("Traced instruction should decode");
+            let size = op.size;
+            let words = program[address..address + size].to_vec();
+            lines.push(instruction_line(&op, address, words, &labels));
+            address += size;
+        } else {
+            // Gather up data until the next instruction or label, whichever comes first
+            let mut end = address + 1;
+            while end < program.len()
+                && end - address < DATA_PER_LINE
+                && !starts.contains(&end)
+                && !labels.contains(&end)
+            {
+                end += 1;
+            }
+            lines.push(Line {
+                address,
+                words: program[address..end].to_vec(),
+                kind: LineKind::Data,
+            });
+            address = end;
+        }
+    }
+    Listing { lines, labels }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::disassembler::*;
+
+    #[test]
+    fn test_disassemble_simple() {
+        let listing = disassemble(&[1001, 5, 7, 9, 99, 3, 0, 0, 0, 0]);
+        assert_eq!(listing.lines[0].text(), "ADD [5], #7 -> [9]");
+        assert_eq!(listing.lines[0].words, vec![1001, 5, 7, 9]);
+        assert_eq!(listing.lines[1].text(), "HLT");
+        assert_eq!(listing.lines[1].address, 4);
+        assert_eq!(listing.lines[2].text(), "DATA 3, 0, 0, 0, 0");
+        assert_eq!(listing.lines.len(), 3);
+    }
+
+    #[test]
+    fn test_disassemble_all_ops() {
+        let program = vec![
+            3, 100, 204, -2, 1105, 1, 9, 99, 0, 1106, 0, 13, 99, 1107, 1, 2, 100, 21108, 1, 1, 3,
+            109, -1, 99,
+        ];
+        let texts: Vec<String> = disassemble(&program)
+            .lines
+            .iter()
+            .map(|l| l.text())
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                "IN -> [100]",
+                "OUT [rb-2]",
+                "JT #1, #L0009",
+                "DATA 99, 0",
+                "JF #0, #L0013",
+                "DATA 99",
+                "LT #1, #2 -> [100]",
+                "EQ #1, #1 -> [rb+3]",
+                "ARB #-1",
+                "HLT",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_disassemble_labels() {
+        // A loop that counts down from 3, outputting as it goes
+        let program = vec![4, 12, 1001, 12, -1, 12, 1005, 12, 0, 99, 0, 0, 3];
+        let listing = disassemble(&program);
+        assert_eq!(listing.labels, BTreeSet::from([0]));
+        assert_eq!(
+            listing.to_string(),
+            "L0000:
+    OUT [12]                                 ; 0000: 4,12
+    ADD [12], #-1 -> [12]                    ; 0002: 1001,12,-1,12
+    JT [12], #L0000                          ; 0006: 1005,12,0
+    HLT                                      ; 0009: 99
+    DATA 0, 0, 3                             ; 0010: 0,0,3
+"
+        );
+    }
+
+    #[test]
+    fn test_disassemble_return_address() {
+        // Push a return address, call a function at 8 which jumps back via the stack
+        let program = vec![21101, 7, 0, 0, 1105, 1, 8, 99, 2106, 0, 0];
+        let listing = disassemble(&program);
+        let texts: Vec<String> = listing.lines.iter().map(|l| l.text()).collect();
+        assert_eq!(
+            texts,
+            vec![
+                "ADD #L0007, #0 -> [rb+0]",
+                "JT #1, #L0008",
+                "HLT",
+                "JF #0, [rb+0]"
+            ]
+        );
+        assert_eq!(listing.labels, BTreeSet::from([7, 8]));
+    }
+
+    #[test]
+    fn test_disassemble_day5() {
+        let input = std::fs::read_to_string("input/intcode/day05.txt").unwrap();
+        let program = crate::intcode::parse_program(&input);
+        let listing = disassemble(&program);
+        // Every word should end up on exactly one line
+        let total: usize = listing.lines.iter().map(|l| l.words.len()).sum();
+        assert_eq!(total, program.len());
+        assert_eq!(listing.lines[0].text(), "IN -> [225]");
+    }
+
+    #[test]
+    fn test_instruction_at() {
+        let memory = Memory::new(vec![1, 2, 3, 1105, 1, 7, 42]);
+        assert_eq!(instruction_at(&memory, 3).text(), "JT #1, #7");
+        assert_eq!(instruction_at(&memory, 3).words, vec![1105, 1, 7]);
+        assert_eq!(instruction_at(&memory, 6).text(), "DATA 42");
+        // Reads past the end are zeros, so it still decodes
+        assert_eq!(instruction_at(&memory, 4).text(), "ADD [7], [42] -> [0]");
+    }
+}
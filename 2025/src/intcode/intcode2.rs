@@ -1,47 +1,51 @@
 
 
 use crate::intcode::intcodecpu::IntCodeCPU;
+use crate::intcode::parse_program;
+use crate::intcode::symbolic::solve_noun_verb;
+use crate::solver::Solver;
 
-fn parse_input(_input: &str) -> Vec<i32> {
-    _input
-        .trim()
-        .split(',')
-        .map(|s| s.parse::<i32>().expect("Failed to parse integer"))
-        .collect()
-}
+pub struct Intcode2;
 
-/*
-* day2, Part 1.
-*/
-pub fn calculate_part1(input: &str) -> usize {
-    let mut program = parse_input(input);
-    program[1] = 12;
-    program[2] = 2;
-    let mut cpu = IntCodeCPU::new(program);
-    cpu.run();
-    cpu.memory[0] as usize
-}
+impl Solver for Intcode2 {
+    const YEAR: u16 = 2019;
+    const DAY: u8 = 2;
+    const NAME: &'static str = "Intcode 2019 Day 2";
+    const INPUT: &'static str = "input/intcode/day02.txt";
+
+    type Input = Vec<i64>;
+    type Part1 = i64;
+    type Part2 = i64;
+
+    fn parse(input: &str) -> Vec<i64> {
+        parse_program(input)
+    }
+
+    /*
+    * day2, Part 1.
+    */
+    fn part1(program: &Vec<i64>) -> i64 {
+        let mut program = program.clone();
+        program[1] = 12;
+        program[2] = 2;
+        let mut cpu = IntCodeCPU::new(program);
+        cpu.run().expect("Intcode program failed");
+        cpu.memory[0]
+    }
 
-/*
- * day2, Part 2
- *
- */
-pub fn calculate_part2(_input: &str) -> usize {
-    for noun in 0..100 {
-        for verb in 0..100 {
-            let mut program = parse_input(_input);
-            program[1] = noun;
-            program[2] = verb;
-            let mut cpu = IntCodeCPU::new(program);
-            cpu.run();
-            if cpu.memory[0] == 19690720 {
-                return (noun * 100 + verb) as usize;
-            }
-        }
+    /*
+     * day2, Part 2
+     *
+     */
+    fn part2(program: &Vec<i64>) -> i64 {
+        // Cell 0 is a polynomial in the noun and verb, so we can solve for them rather than trying them all
+        let (noun, verb) =
+            solve_noun_verb(program, 19690720).expect("No noun and verb give 19690720");
+        noun * 100 + verb
     }
-    return 0    
 }
 
+crate::register_solver!(Intcode2);
 
 #[cfg(test)]
 mod tests {
@@ -50,15 +54,15 @@ mod tests {
     #[test]
     fn test_generator() {
         let input = "1,9,10,3,2,3,11,0,99,30,40,50";
-        assert_eq!(parse_input(&input), vec![1,9,10,3,2,3,11,0,99,30,40,50]);
+        assert_eq!(parse_program(input), vec![1,9,10,3,2,3,11,0,99,30,40,50]);
     }
 
     #[test]
     fn test_example_part1() {
         let input = "1,9,10,3,2,3,11,0,99,30,40,50";
-        let program = parse_input(input);
+        let program = parse_program(input);
         let mut cpu = IntCodeCPU::new(program);
-        cpu.run();
+        cpu.run().unwrap();
         assert_eq!(cpu.memory[0] as usize, 3500);
     }
 }
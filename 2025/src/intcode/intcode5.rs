@@ -1,28 +1,152 @@
-use crate::intcode::intcodecpu::IntCodeCPU;
-
-fn parse_input(_input: &str) -> Vec<i32> {
-    _input
-        .trim()
-        .split(',')
-        .map(|s| s.parse::<i32>().expect("Failed to parse integer"))
-        .collect()
+use crate::intcode::intcodecpu::{IntCodeCPU, IntCodeError, RunStatus};
+use crate::intcode::parse_program;
+use crate::solver::Solver;
+use std::fmt;
+
+/* The TEST diagnostic outputs a 0 for every check that passed, and then the diagnostic code as the
+ * very last output.  Anything else before the end means that check failed, and the answer can't be
+ * trusted, which usually means a bug in one of the opcodes */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticError {
+    Cpu(IntCodeError),
+    // Which check it was (counting from 0), what it output and where the output instruction is
+    FailedCheck { index: usize, value: i64, pc: usize },
+    NoOutput,
+}
+
+impl fmt::Display for DiagnosticError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiagnosticError::Cpu(error) => write!(f, "{}", error),
+            DiagnosticError::FailedCheck { index, value, pc } => write!(
+                f,
+                "diagnostic check {} failed with {} (output at pc {})",
+                index, value, pc
+            ),
+            DiagnosticError::NoOutput => write!(f, "diagnostic halted without a code"),
+        }
+    }
+}
+
+impl std::error::Error for DiagnosticError {}
+
+/* Checks every output as it arrives.  We don't know an output is a check until we see there's
+ * another one after it, so the latest one is held back until then */
+#[derive(Debug, Default)]
+struct Checks {
+    last: Option<(i64, usize)>,
+    index: usize,
+    failed: Option<DiagnosticError>,
+}
+
+impl Checks {
+    fn record(&mut self, value: i64, pc: usize) {
+        if let Some((check, check_pc)) = self.last {
+            if check != 0 && self.failed.is_none() {
+                self.failed = Some(DiagnosticError::FailedCheck {
+                    index: self.index,
+                    value: check,
+                    pc: check_pc,
+                });
+            }
+            self.index += 1;
+        }
+        self.last = Some((value, pc));
+    }
+}
+
+/* Run the diagnostic for a system, returning the diagnostic code once every check has passed.  We step
+ * one instruction at a time so we know which one each output came from */
+pub fn run_diagnostic(program: Vec<i64>, system_id: i64) -> Result<i64, DiagnosticError> {
+    let mut cpu = IntCodeCPU::new_with_io(program, vec![system_id]);
+    let mut checks = Checks::default();
+    loop {
+        let pc = cpu.pc;
+        match cpu.execute().map_err(DiagnosticError::Cpu)? {
+            RunStatus::Output(value) => checks.record(value, pc),
+            RunStatus::NeedsInput => return Err(DiagnosticError::Cpu(cpu.input_underflow())),
+            RunStatus::Halted => break,
+            RunStatus::Running => {}
+        }
+    }
+    match checks.failed {
+        Some(error) => Err(error),
+        None => checks.last.map(|(v, _)| v).ok_or(DiagnosticError::NoOutput),
+    }
 }
 
-/*
-* day2, Part 1.
-*/
-pub fn calculate_part1(input: &str) -> usize {
-    let program = parse_input(input);
-    let mut cpu = IntCodeCPU::new_with_io(program, vec![1]);
-    cpu.run();
-    println!("Output: {:?}", cpu.output);
-    cpu.output.last().cloned().unwrap_or(0) as usize
+pub struct Intcode5;
+
+impl Solver for Intcode5 {
+    const YEAR: u16 = 2019;
+    const DAY: u8 = 5;
+    const NAME: &'static str = "Intcode 2019 Day 5";
+    const INPUT: &'static str = "input/intcode/day05.txt";
+
+    type Input = Vec<i64>;
+    type Part1 = i64;
+    type Part2 = i64;
+
+    fn parse(input: &str) -> Vec<i64> {
+        parse_program(input)
+    }
+
+    /*
+     * day5, Part 1.  The air conditioner is system 1
+     */
+    fn part1(program: &Vec<i64>) -> i64 {
+        run_diagnostic(program.clone(), 1).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /*
+     * day5, Part 2.  The thermal radiator controller is system 5, which only outputs the code
+     */
+    fn part2(program: &Vec<i64>) -> i64 {
+        run_diagnostic(program.clone(), 5).unwrap_or_else(|e| panic!("{}", e))
+    }
 }
 
-/*
- * day2, Part 2
- *
- */
-pub fn calculate_part2(_input: &str) -> usize {
-    return 0    
+crate::register_solver!(Intcode5);
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::intcode5::*;
+
+    #[test]
+    fn test_diagnostic_checks() {
+        // Two passing checks and then the code
+        assert_eq!(
+            run_diagnostic(parse_program("104,0,104,0,104,42,99"), 1),
+            Ok(42)
+        );
+        // The second check fails, and only the code is allowed to be non-zero
+        assert_eq!(
+            run_diagnostic(parse_program("104,0,104,3,104,42,99"), 1),
+            Err(DiagnosticError::FailedCheck {
+                index: 1,
+                value: 3,
+                pc: 2
+            })
+        );
+        // A check that works out its value first, so the output isn't at the start
+        assert_eq!(
+            run_diagnostic(parse_program("1101,0,7,9,4,9,104,1,99,0"), 1),
+            Err(DiagnosticError::FailedCheck {
+                index: 0,
+                value: 7,
+                pc: 4
+            })
+        );
+        assert_eq!(
+            run_diagnostic(parse_program("99"), 1),
+            Err(DiagnosticError::NoOutput)
+        );
+    }
+
+    #[test]
+    fn test_real_input() {
+        let input = std::fs::read_to_string("input/intcode/day05.txt").unwrap();
+        assert_eq!(Intcode5::solve_part1(&input), 9654885);
+        assert_eq!(Intcode5::solve_part2(&input), 7079459);
+    }
 }
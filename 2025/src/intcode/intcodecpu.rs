@@ -1,168 +1,206 @@
+use crate::intcode::decode::DecodeCache;
+use crate::intcode::memory::Memory;
+use crate::intcode::profile::Profile;
+use std::collections::VecDeque;
+use std::fmt;
+
+#[derive(Debug, Clone)]
 pub struct IntCodeCPU {
-    pub memory: Vec<i32>,
+    pub memory: Memory,
     pub pc: usize,
+    pub relative_base: i64,
     pub running: bool,
-    pub input: Vec<i32>,
-    pub output: Vec<i32>,
+    pub input: VecDeque<i64>,
+    pub output: Vec<i64>,
+    pub(crate) decoded: DecodeCache,
+    pub(crate) profile: Option<Box<Profile>>,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Modes {
     Position,
     Immediate,
+    Relative,
 }
 
 impl Modes {
-    pub fn from(value: i32) -> Self {
+    pub fn from(value: i64) -> Result<Self, IntCodeErrorKind> {
         match value {
-            0 => Modes::Position,
-            1 => Modes::Immediate,
-            _ => panic!("Unknown mode: {}", value),
+            0 => Ok(Modes::Position),
+            1 => Ok(Modes::Immediate),
+            2 => Ok(Modes::Relative),
+            _ => Err(IntCodeErrorKind::UnknownMode(value)),
         }
     }
 }
 
-pub struct IntCodeOpParams {
-    modes: Vec<Modes>,
-    size: usize,
+/* Everything that can go wrong inside the CPU.  The op doesn't know where it is in memory, so it
+ * just reports the kind, and the CPU wraps that up with the pc and raw opcode */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntCodeErrorKind {
+    UnknownOpcode,
+    UnknownMode(i64),
+    ImmediateWrite,
+    NegativeAddress(i64),
+    InputUnderflow,
+    // A sum or product too big for an i64, including working out a relative address
+    Overflow,
 }
-pub enum IntCodeOp {
-    Add(IntCodeOpParams),
-    Mul(IntCodeOpParams),
-    Input(IntCodeOpParams),
-    Output(IntCodeOpParams),
-    Halt(IntCodeOpParams),
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntCodeError {
+    pub pc: usize,
+    pub opcode: i64,
+    pub kind: IntCodeErrorKind,
 }
 
-impl IntCodeOp {
-    pub fn from_opcode(opcode: i32) -> Self {
-        match opcode % 100 {
-            1 => IntCodeOp::Add(IntCodeOpParams {
-                modes: vec![
-                    Modes::from((opcode / 100) % 10),
-                    Modes::from((opcode / 1000) % 10),
-                ],
-                size: 4,
-            }),
-            2 => IntCodeOp::Mul(IntCodeOpParams {
-                modes: vec![
-                    Modes::from((opcode / 100) % 10),
-                    Modes::from((opcode / 1000) % 10),
-                ],
-                size: 4,
-            }),
-            3 => IntCodeOp::Input(IntCodeOpParams {
-                modes: vec![Modes::from((opcode / 100) % 10)],
-                size: 2,
-            }),
-            4 => IntCodeOp::Output(IntCodeOpParams {
-                modes: vec![Modes::from((opcode / 100) % 10)],
-                size: 2,
-            }),
-            99 => IntCodeOp::Halt(IntCodeOpParams {
-                size: 1,
-                modes: vec![],
-            }),
-            _ => panic!("Unknown opcode: {}", opcode),
-        }
-    }
-    pub fn get_parameter(&self, cpu: &mut IntCodeCPU, offset: usize, mode: Modes) -> i32 {
-        let param_value = cpu.memory[cpu.pc + offset];
-        match mode {
-            Modes::Position => {
-                cpu.memory[param_value as usize] // Position mode
-            }
-            Modes::Immediate => param_value, // Immediate mode
+impl fmt::Display for IntCodeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntCodeErrorKind::UnknownOpcode => write!(f, "unknown opcode"),
+            IntCodeErrorKind::UnknownMode(mode) => write!(f, "unknown parameter mode {}", mode),
+            IntCodeErrorKind::ImmediateWrite => write!(f, "write target in immediate mode"),
+            IntCodeErrorKind::NegativeAddress(address) => write!(f, "negative address {}", address),
+            IntCodeErrorKind::InputUnderflow => write!(f, "no input available"),
+            IntCodeErrorKind::Overflow => write!(f, "arithmetic overflow"),
         }
     }
+}
 
-    pub fn execute(&self, cpu: &mut IntCodeCPU) {
-        match self {
-            IntCodeOp::Add(params) => {
-                let target = cpu.memory[cpu.pc + 3] as usize;
-                let p1 = self.get_parameter(cpu, 1, params.modes[0]);
-                let p2 = self.get_parameter(cpu, 2, params.modes[1]);
-                cpu.memory[target] = p1 + p2;
-                cpu.pc += params.size;
-            }
-            IntCodeOp::Mul(params) => {
-                let target = cpu.memory[cpu.pc + 3] as usize;
-                let p1 = self.get_parameter(cpu, 1, params.modes[0]);
-                let p2 = self.get_parameter(cpu, 2, params.modes[1]);
-                cpu.memory[target] = p1 * p2;
-                cpu.pc += params.size;
-            }
-            IntCodeOp::Input(params) => {
-                let target = cpu.memory[cpu.pc + 1] as usize;
-                if cpu.input.is_empty() {
-                    panic!("No input available for IntCode Input operation");
-                }
-                let input_value = cpu.input.remove(0);
-                match params.modes[0] {
-                    Modes::Position => {cpu.memory[target] = input_value}   // Position mode
-                    Modes::Immediate => {panic!("Writes should never be immediate: {}", input_value)} // Immediate mode
-                }
-                cpu.pc += params.size;
-            }
-            IntCodeOp::Output(params) => {
-                let target = cpu.memory[cpu.pc + 1];
-                match params.modes[0] {
-                    Modes::Position => {cpu.output.push(cpu.memory[target as usize]);}   // Position mode
-                    Modes::Immediate => {cpu.output.push(target)} // Immediate mode
-                }
-                cpu.pc += params.size;
-            }
-            IntCodeOp::Halt(params) => {
-                cpu.running = false;
-                cpu.pc += params.size;
-            }
-        }
+impl fmt::Display for IntCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at pc {} (opcode {})",
+            self.kind, self.pc, self.opcode
+        )
     }
 }
 
+impl std::error::Error for IntCodeError {}
+
+/* What happened when the CPU last stopped.  Running means it executed an instruction and can carry on,
+ * the others are the points where a caller driving the CPU by hand needs to do something.  The symbolic
+ * CPU outputs something other than plain numbers, everything else can just say RunStatus */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunStatus<V = i64> {
+    Running,
+    NeedsInput,
+    Output(V),
+    Halted,
+}
+
+// Addresses are stored as plain intcode values, but a negative one can never be valid
+pub(crate) fn to_address(value: i64) -> Result<usize, IntCodeErrorKind> {
+    usize::try_from(value).map_err(|_| IntCodeErrorKind::NegativeAddress(value))
+}
+
+// A parameter plus the base it's relative to (0 in position mode)
+pub(crate) fn relative_address(base: i64, value: i64) -> Result<usize, IntCodeErrorKind> {
+    to_address(base.checked_add(value).ok_or(IntCodeErrorKind::Overflow)?)
+}
+
 impl IntCodeCPU {
-    pub fn new(program: Vec<i32>) -> Self {
+    pub fn new(program: Vec<i64>) -> Self {
         IntCodeCPU {
-            memory: program,
+            memory: Memory::new(program),
             pc: 0,
+            relative_base: 0,
             running: true,
-            input: vec![],
+            input: VecDeque::new(),
             output: Vec::new(),
+            decoded: DecodeCache::default(),
+            profile: None,
         }
     }
-    pub fn new_with_io(program: Vec<i32>, input: Vec<i32>) -> Self {
+    pub fn new_with_io(program: Vec<i64>, input: Vec<i64>) -> Self {
         IntCodeCPU {
-            memory: program,
+            memory: Memory::new(program),
             pc: 0,
+            relative_base: 0,
             running: true,
-            input: input,
+            input: input.into(),
             output: Vec::new(),
+            decoded: DecodeCache::default(),
+            profile: None,
+        }
+    }
+    pub fn execute(&mut self) -> Result<RunStatus, IntCodeError> {
+        if !self.running {
+            return Ok(RunStatus::Halted);
+        }
+        let pc = self.pc;
+        let opcode_value = self.memory.get(pc);
+        let error = |kind| IntCodeError {
+            pc,
+            opcode: opcode_value,
+            kind,
+        };
+        let instruction = self
+            .decoded
+            .decode(pc, opcode_value, self.memory.dense().len())
+            .map_err(error)?;
+        let status = instruction.execute(self).map_err(error)?;
+        // Waiting for input didn't actually run anything, so it doesn't count
+        if let Some(profile) = &mut self.profile
+            && status != RunStatus::NeedsInput
+        {
+            profile.record(pc, &instruction);
         }
+        Ok(status)
     }
-    pub fn execute(&mut self) {
-        let opcode_value = self.memory[self.pc];
-        let opcode = IntCodeOp::from_opcode(opcode_value);
-        opcode.execute(self);
+
+    // For callers that want input the CPU is never going to get
+    pub fn input_underflow(&self) -> IntCodeError {
+        IntCodeError {
+            pc: self.pc,
+            opcode: self.memory.get(self.pc),
+            kind: IntCodeErrorKind::InputUnderflow,
+        }
     }
 
-    pub fn run(&mut self) {
+    pub fn push_input(&mut self, value: i64) {
+        self.input.push_back(value);
+    }
+
+    /* Run until something interesting happens, either we need input that isn't there, we've produced a
+     * value or we've halted.  Outputs are only returned, it's up to the caller to keep them if it wants.
+     * If we need input, push some and then resume again, we'll pick up at the same instruction */
+    pub fn resume(&mut self) -> Result<RunStatus, IntCodeError> {
+        loop {
+            match self.execute()? {
+                RunStatus::Running => {}
+                status => return Ok(status),
+            }
+        }
+    }
+
+    /* Run the program to the end, it's expected that all input is already available.  This is the one
+     * place outputs are collected in self.output, everything driving the CPU by hand gets them back
+     * from resume, and run_with sends them to a sink */
+    pub fn run(&mut self) -> Result<(), IntCodeError> {
         while self.running {
-            self.execute();
+            match self.resume()? {
+                RunStatus::NeedsInput => return Err(self.input_underflow()),
+                RunStatus::Output(value) => self.output.push(value),
+                _ => {}
+            }
         }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::intcode::intcodecpu::IntCodeCPU;
+    use crate::intcode::intcodecpu::{IntCodeCPU, IntCodeError, IntCodeErrorKind, RunStatus};
 
     #[test]
     fn test_intcode_halt() {
         let mut cpu = IntCodeCPU::new(vec![99]);
-        assert_eq!(cpu.running, true);
+        assert!(cpu.running);
         assert_eq!(cpu.pc, 0);
-        cpu.execute();
-        assert_eq!(cpu.running, false);
+        cpu.execute().unwrap();
+        assert!(!cpu.running);
         assert_eq!(cpu.pc, 1);
     }
 
@@ -171,7 +209,7 @@ mod tests {
         let mut cpu = IntCodeCPU::new(vec![1, 0, 0, 0, 99]);
         assert_eq!(cpu.memory[0], 1);
         assert_eq!(cpu.pc, 0);
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.pc, 4);
         assert_eq!(cpu.memory[0], 2);
     }
@@ -181,7 +219,7 @@ mod tests {
         let mut cpu = IntCodeCPU::new(vec![2, 3, 0, 3, 99]);
         assert_eq!(cpu.memory[3], 3);
         assert_eq!(cpu.pc, 0);
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.pc, 4);
         assert_eq!(cpu.memory[3], 6);
     }
@@ -191,7 +229,7 @@ mod tests {
         let mut cpu = IntCodeCPU::new(vec![2, 4, 4, 5, 99, 0]);
         assert_eq!(cpu.memory[5], 0);
         assert_eq!(cpu.pc, 0);
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.pc, 4);
         assert_eq!(cpu.memory[5], 9801);
     }
@@ -201,27 +239,27 @@ mod tests {
         let mut cpu = IntCodeCPU::new(vec![1, 1, 1, 4, 99, 5, 6, 0, 99]);
         assert_eq!(cpu.memory[4], 99);
         assert_eq!(cpu.pc, 0);
-        assert_eq!(cpu.running, true);
-        cpu.execute();
+        assert!(cpu.running);
+        cpu.execute().unwrap();
         assert_eq!(cpu.pc, 4);
-        assert_eq!(cpu.running, true);
+        assert!(cpu.running);
         assert_eq!(cpu.memory[4], 2);
         assert_eq!(cpu.memory[0], 1);
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.pc, 8);
-        assert_eq!(cpu.running, true);
+        assert!(cpu.running);
         assert_eq!(cpu.memory[4], 2);
         assert_eq!(cpu.memory[0], 30);
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.pc, 9);
-        assert_eq!(cpu.running, false);
+        assert!(!cpu.running);
     }
 
     #[test]
     fn test_intcode_add_value_value() {
         let mut cpu = IntCodeCPU::new(vec![1101, 5, 7, 0, 99]);
         assert_eq!(cpu.memory[0], 1101);
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.pc, 4);
         assert_eq!(cpu.memory[0], 12);
     }
@@ -230,7 +268,7 @@ mod tests {
     fn test_intcode_mul_value_value() {
         let mut cpu = IntCodeCPU::new(vec![1002, 4, 3, 4, 33]);
         assert_eq!(cpu.memory[0], 1002);
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.pc, 4);
         assert_eq!(cpu.memory[4], 99);
     }
@@ -238,7 +276,7 @@ mod tests {
     fn test_intcode_add_negative() {
         let mut cpu = IntCodeCPU::new(vec![1101, 100, -1, 4, 0]);
         assert_eq!(cpu.memory[4], 0);
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.pc, 4);
         assert_eq!(cpu.memory[4], 99);
     }
@@ -255,7 +293,7 @@ mod tests {
             (30, vec![1102, 5, 6, 4, 0, 10, 20]),
         ] {
             let mut cpu = IntCodeCPU::new(program.clone());
-            cpu.execute();
+            cpu.execute().unwrap();
             assert_eq!(cpu.memory[4], *expected);
         }
     }
@@ -264,7 +302,7 @@ mod tests {
         let mut cpu = IntCodeCPU::new_with_io(vec![3, 2, 0], vec![99]);
         assert_eq!(cpu.input, vec![99]);
         assert_eq!(cpu.memory[2], 0);
-        cpu.execute();
+        cpu.execute().unwrap();
         assert_eq!(cpu.input, vec![]);
         assert_eq!(cpu.pc, 2);
         assert_eq!(cpu.memory[2], 99);
@@ -272,17 +310,13 @@ mod tests {
     #[test]
     fn test_intcode_output() {
         let mut cpu = IntCodeCPU::new_with_io(vec![4, 2, 99], vec![]);
-        assert_eq!(cpu.output, vec![]);
-        cpu.execute();
-        assert_eq!(cpu.output, vec![99]);
+        assert_eq!(cpu.execute().unwrap(), RunStatus::Output(99));
         assert_eq!(cpu.pc, 2);
     }
     #[test]
     fn test_intcode_immediate_output() {
         let mut cpu = IntCodeCPU::new_with_io(vec![104, 2, 99], vec![]);
-        assert_eq!(cpu.output, vec![]);
-        cpu.execute();
-        assert_eq!(cpu.output, vec![2]);
+        assert_eq!(cpu.execute().unwrap(), RunStatus::Output(2));
         assert_eq!(cpu.pc, 2);
     }
     #[test]
@@ -290,8 +324,209 @@ mod tests {
         let mut cpu =
             IntCodeCPU::new_with_io(vec![3, 9, 1001, 9, 5, 10, 4, 10, 99, 0, 0], vec![52]);
         assert_eq!(cpu.output, vec![]);
-        cpu.run();
+        cpu.run().unwrap();
         assert_eq!(cpu.memory, vec![3, 9, 1001, 9, 5, 10, 4, 10, 99, 52, 57]);
         assert_eq!(cpu.output, vec![57]);
     }
+
+    #[test]
+    fn test_intcode_jumps() {
+        // Jump if true, 1105 with a non-zero value jumps over the output
+        let mut cpu = IntCodeCPU::new(vec![1105, 1, 4, 99, 104, 7, 99]);
+        cpu.run().unwrap();
+        assert_eq!(cpu.output, vec![7]);
+        // Jump if false, 1106 with a non-zero value falls through to the halt
+        let mut cpu = IntCodeCPU::new(vec![1106, 1, 4, 99, 104, 7, 99]);
+        cpu.execute().unwrap();
+        assert_eq!(cpu.pc, 3);
+        cpu.run().unwrap();
+        assert_eq!(cpu.output, vec![]);
+    }
+    #[test]
+    fn test_intcode_day5_equal_and_less_than() {
+        for (program, input, expected) in &[
+            // Position mode, is the input equal to 8
+            (vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8], 8, 1),
+            (vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8], 7, 0),
+            // Position mode, is the input less than 8
+            (vec![3, 9, 7, 9, 10, 9, 4, 9, 99, -1, 8], 7, 1),
+            (vec![3, 9, 7, 9, 10, 9, 4, 9, 99, -1, 8], 8, 0),
+            // Immediate mode, is the input equal to 8
+            (vec![3, 3, 1108, -1, 8, 3, 4, 3, 99], 8, 1),
+            (vec![3, 3, 1108, -1, 8, 3, 4, 3, 99], 9, 0),
+            // Immediate mode, is the input less than 8
+            (vec![3, 3, 1107, -1, 8, 3, 4, 3, 99], 3, 1),
+            (vec![3, 3, 1107, -1, 8, 3, 4, 3, 99], 9, 0),
+        ] {
+            let mut cpu = IntCodeCPU::new_with_io(program.clone(), vec![*input]);
+            cpu.run().unwrap();
+            assert_eq!(cpu.output, vec![*expected]);
+        }
+    }
+    #[test]
+    fn test_intcode_day5_jump_tests() {
+        for (program, input, expected) in &[
+            (
+                vec![3, 12, 6, 12, 15, 1, 13, 14, 13, 4, 13, 99, -1, 0, 1, 9],
+                0,
+                0,
+            ),
+            (
+                vec![3, 12, 6, 12, 15, 1, 13, 14, 13, 4, 13, 99, -1, 0, 1, 9],
+                5,
+                1,
+            ),
+            (vec![3, 3, 1105, -1, 9, 1101, 0, 0, 12, 4, 12, 99, 1], 0, 0),
+            (vec![3, 3, 1105, -1, 9, 1101, 0, 0, 12, 4, 12, 99, 1], 5, 1),
+        ] {
+            let mut cpu = IntCodeCPU::new_with_io(program.clone(), vec![*input]);
+            cpu.run().unwrap();
+            assert_eq!(cpu.output, vec![*expected]);
+        }
+    }
+    #[test]
+    fn test_intcode_day5_larger_example() {
+        let program = vec![
+            3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0,
+            0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4,
+            20, 1105, 1, 46, 98, 99,
+        ];
+        for (input, expected) in &[(7, 999), (8, 1000), (9, 1001)] {
+            let mut cpu = IntCodeCPU::new_with_io(program.clone(), vec![*input]);
+            cpu.run().unwrap();
+            assert_eq!(cpu.output, vec![*expected]);
+        }
+    }
+    #[test]
+    fn test_intcode_relative_base() {
+        // From the day 9 description, with a relative base of 2000, 109,19 moves it to 2019 and 204,-34 outputs address 1985
+        let mut cpu = IntCodeCPU::new(vec![109, 19, 204, -34, 99]);
+        cpu.memory[1985] = 42;
+        cpu.relative_base = 2000;
+        cpu.execute().unwrap();
+        assert_eq!(cpu.relative_base, 2019);
+        cpu.run().unwrap();
+        assert_eq!(cpu.output, vec![42]);
+    }
+    #[test]
+    fn test_intcode_relative_write() {
+        // Input into relative base + 1, then output it back
+        let mut cpu = IntCodeCPU::new_with_io(vec![109, 6, 203, 1, 204, 1, 99, 0], vec![55]);
+        cpu.run().unwrap();
+        assert_eq!(cpu.memory[7], 55);
+        assert_eq!(cpu.output, vec![55]);
+    }
+    #[test]
+    fn test_intcode_day9_quine() {
+        let program = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        let mut cpu = IntCodeCPU::new(program.clone());
+        cpu.run().unwrap();
+        assert_eq!(cpu.output, program);
+    }
+    #[test]
+    fn test_intcode_day9_large_numbers() {
+        let mut cpu = IntCodeCPU::new(vec![1102, 34915192, 34915192, 7, 4, 7, 99, 0]);
+        cpu.run().unwrap();
+        assert_eq!(cpu.output[0].to_string().len(), 16);
+        assert_eq!(cpu.output, vec![1219070632396864]);
+
+        let mut cpu = IntCodeCPU::new(vec![104, 1125899906842624, 99]);
+        cpu.run().unwrap();
+        assert_eq!(cpu.output, vec![1125899906842624]);
+    }
+    #[test]
+    fn test_intcode_memory_beyond_program() {
+        // Reading past the end gives 0, and writing past the end grows memory
+        let mut cpu = IntCodeCPU::new(vec![1, 100, 101, 50, 1101, 3, 4, 1000, 4, 1000, 99]);
+        cpu.run().unwrap();
+        assert_eq!(cpu.memory[50], 0);
+        assert_eq!(cpu.memory[1000], 7);
+        assert_eq!(cpu.output, vec![7]);
+    }
+    #[test]
+    fn test_intcode_resume_waits_for_input() {
+        // Read two numbers, output their sum, and loop back round
+        let mut cpu = IntCodeCPU::new(vec![
+            3, 13, 3, 14, 1, 13, 14, 15, 4, 15, 1105, 1, 0, 0, 0, 0,
+        ]);
+        assert_eq!(cpu.resume().unwrap(), RunStatus::NeedsInput);
+        assert_eq!(cpu.pc, 0);
+        cpu.push_input(3);
+        assert_eq!(cpu.resume().unwrap(), RunStatus::NeedsInput);
+        assert_eq!(cpu.pc, 2);
+        cpu.push_input(4);
+        assert_eq!(cpu.resume().unwrap(), RunStatus::Output(7));
+        cpu.push_input(10);
+        cpu.push_input(-2);
+        assert_eq!(cpu.resume().unwrap(), RunStatus::Output(8));
+        assert_eq!(cpu.resume().unwrap(), RunStatus::NeedsInput);
+        // Whoever called resume has had the outputs, so the CPU doesn't hang on to them
+        assert!(cpu.output.is_empty());
+    }
+    #[test]
+    fn test_intcode_resume_outputs_then_halts() {
+        let mut cpu = IntCodeCPU::new(vec![104, 1, 104, 2, 99]);
+        assert_eq!(cpu.resume().unwrap(), RunStatus::Output(1));
+        assert_eq!(cpu.resume().unwrap(), RunStatus::Output(2));
+        assert_eq!(cpu.resume().unwrap(), RunStatus::Halted);
+        assert_eq!(cpu.resume().unwrap(), RunStatus::Halted);
+        assert_eq!(cpu.pc, 5);
+    }
+    #[test]
+    fn test_intcode_run_without_input() {
+        let mut cpu = IntCodeCPU::new(vec![1101, 1, 1, 0, 3, 0, 99]);
+        assert_eq!(
+            cpu.run(),
+            Err(IntCodeError {
+                pc: 4,
+                opcode: 3,
+                kind: IntCodeErrorKind::InputUnderflow
+            })
+        );
+    }
+    #[test]
+    fn test_intcode_errors() {
+        for (program, expected) in &[
+            (vec![42, 0, 0], IntCodeErrorKind::UnknownOpcode),
+            (vec![301, 0, 0, 0], IntCodeErrorKind::UnknownMode(3)),
+            (vec![11101, 1, 1, 0], IntCodeErrorKind::ImmediateWrite),
+            (vec![103, 0], IntCodeErrorKind::ImmediateWrite),
+            (vec![1, -1, 0, 0], IntCodeErrorKind::NegativeAddress(-1)),
+            (vec![109, -5, 204, 0], IntCodeErrorKind::NegativeAddress(-5)),
+            (vec![1105, 1, -3], IntCodeErrorKind::NegativeAddress(-3)),
+            (vec![1101, i64::MAX, 1, 0], IntCodeErrorKind::Overflow),
+            (vec![1102, i64::MAX, 2, 0], IntCodeErrorKind::Overflow),
+            (vec![109, i64::MAX, 109, 1], IntCodeErrorKind::Overflow),
+            (vec![109, i64::MAX, 204, 1], IntCodeErrorKind::Overflow),
+        ] {
+            let mut cpu = IntCodeCPU::new_with_io(program.clone(), vec![1]);
+            let error = cpu.run().unwrap_err();
+            assert_eq!(error.kind, *expected);
+        }
+    }
+    #[test]
+    fn test_intcode_overflow() {
+        // Doubling 2^62 doesn't fit, which is an error a search can throw away rather than a panic
+        let mut cpu = IntCodeCPU::new(vec![1101, 1, 1, 9, 1102, 1 << 62, 2, 9, 99, 0]);
+        assert_eq!(
+            cpu.run(),
+            Err(IntCodeError {
+                pc: 4,
+                opcode: 1102,
+                kind: IntCodeErrorKind::Overflow
+            })
+        );
+        assert_eq!(cpu.memory[9], 2);
+    }
+    #[test]
+    fn test_intcode_error_reports_position() {
+        // Overwrite our own next instruction with garbage
+        let mut cpu = IntCodeCPU::new(vec![1101, 40, 2, 4, 0, 99]);
+        let error = cpu.run().unwrap_err();
+        assert_eq!(error.pc, 4);
+        assert_eq!(error.opcode, 42);
+        assert_eq!(error.to_string(), "unknown opcode at pc 4 (opcode 42)");
+    }
 }
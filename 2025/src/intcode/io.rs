@@ -0,0 +1,263 @@
+use crate::intcode::intcodecpu::{IntCodeCPU, IntCodeError, RunStatus};
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{Receiver, Sender};
+
+/* Somewhere for a CPU to get its input from when it runs out.  None means there's never going to be
+ * any more, so the CPU can't go on */
+pub trait InputSource {
+    fn read(&mut self) -> Option<i64>;
+}
+
+// And somewhere for its output to go
+pub trait OutputSink {
+    fn write(&mut self, value: i64);
+}
+
+impl<T: InputSource + ?Sized> InputSource for &mut T {
+    fn read(&mut self) -> Option<i64> {
+        (**self).read()
+    }
+}
+
+impl<T: OutputSink + ?Sized> OutputSink for &mut T {
+    fn write(&mut self, value: i64) {
+        (**self).write(value)
+    }
+}
+
+impl InputSource for VecDeque<i64> {
+    fn read(&mut self) -> Option<i64> {
+        self.pop_front()
+    }
+}
+
+impl OutputSink for Vec<i64> {
+    fn write(&mut self, value: i64) {
+        self.push(value);
+    }
+}
+
+impl OutputSink for VecDeque<i64> {
+    fn write(&mut self, value: i64) {
+        self.push_back(value);
+    }
+}
+
+// Blocks until another thread sends something, and ends once every sender has gone away
+impl InputSource for Receiver<i64> {
+    fn read(&mut self) -> Option<i64> {
+        self.recv().ok()
+    }
+}
+
+// If whoever was listening has gone away, nobody cares about the output so we just drop it
+impl OutputSink for Sender<i64> {
+    fn write(&mut self, value: i64) {
+        let _ = self.send(value);
+    }
+}
+
+/* Closures, for things like a robot controller that works out its next input from where it is.
+ * These are wrapped up rather than implemented for FnMut directly, so they don't clash with the rest */
+pub struct InputFn<F>(pub F);
+
+impl<F: FnMut() -> Option<i64>> InputSource for InputFn<F> {
+    fn read(&mut self) -> Option<i64> {
+        (self.0)()
+    }
+}
+
+pub struct OutputFn<F>(pub F);
+
+impl<F: FnMut(i64)> OutputSink for OutputFn<F> {
+    fn write(&mut self, value: i64) {
+        (self.0)(value)
+    }
+}
+
+/* Numbers from anything we can read lines from, like stdin.  They can be separated by commas or
+ * whitespace, so you can paste in a list or type them one at a time.  Anything that isn't a number
+ * ends the input, the same as running out */
+pub struct ReaderInput<R> {
+    reader: R,
+    pending: VecDeque<i64>,
+    finished: bool,
+}
+
+impl<R: BufRead> ReaderInput<R> {
+    pub fn new(reader: R) -> Self {
+        ReaderInput {
+            reader,
+            pending: VecDeque::new(),
+            finished: false,
+        }
+    }
+}
+
+impl ReaderInput<io::StdinLock<'static>> {
+    pub fn stdin() -> Self {
+        ReaderInput::new(io::stdin().lock())
+    }
+}
+
+impl<R: BufRead> InputSource for ReaderInput<R> {
+    fn read(&mut self) -> Option<i64> {
+        while self.pending.is_empty() {
+            let mut line = String::new();
+            if self.finished || self.reader.read_line(&mut line).ok()? == 0 {
+                return None;
+            }
+            for number in line.split(|c: char| c == ',' || c.is_whitespace()) {
+                if number.is_empty() {
+                    continue;
+                }
+                match number.parse() {
+                    Ok(value) => self.pending.push_back(value),
+                    Err(_) => {
+                        self.finished = true;
+                        break;
+                    }
+                }
+            }
+        }
+        self.pending.pop_front()
+    }
+}
+
+// One number per line, flushed straight away so someone watching a terminal sees it as it happens
+pub struct WriterOutput<W> {
+    writer: W,
+}
+
+impl<W: Write> WriterOutput<W> {
+    pub fn new(writer: W) -> Self {
+        WriterOutput { writer }
+    }
+}
+
+impl WriterOutput<io::Stdout> {
+    pub fn stdout() -> Self {
+        WriterOutput::new(io::stdout())
+    }
+}
+
+impl<W: Write> OutputSink for WriterOutput<W> {
+    fn write(&mut self, value: i64) {
+        writeln!(self.writer, "{}", value).expect("Failed to write output");
+        self.writer.flush().expect("Failed to write output");
+    }
+}
+
+impl IntCodeCPU {
+    /* Run to the end, reading from input whenever the queue runs dry and sending every output to the
+     * sink.  Anything already pushed as input is used first.  Outputs only go to the sink, so a long
+     * running program doesn't pile them up in memory */
+    pub fn run_with(
+        &mut self,
+        mut input: impl InputSource,
+        mut output: impl OutputSink,
+    ) -> Result<(), IntCodeError> {
+        loop {
+            match self.resume()? {
+                RunStatus::Output(value) => output.write(value),
+                RunStatus::NeedsInput => match input.read() {
+                    Some(value) => self.push_input(value),
+                    None => return Err(self.input_underflow()),
+                },
+                RunStatus::Halted => return Ok(()),
+                RunStatus::Running => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::assembler::assemble;
+    use crate::intcode::intcodecpu::IntCodeErrorKind;
+    use crate::intcode::io::*;
+    use std::sync::mpsc;
+    use std::thread;
+
+    // Outputs double every number it reads, stopping after it's passed on a 0
+    fn doubler() -> IntCodeCPU {
+        IntCodeCPU::new(
+            assemble(
+                "   loop:   IN -> [x]
+                            MUL [x], #2 -> [x]
+                            OUT [x]
+                            JT [x], #loop
+                            HLT
+                    x:      DATA 0",
+            )
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_run_with_queues() {
+        let mut output = vec![];
+        doubler()
+            .run_with(VecDeque::from([1, 2, 3, 0]), &mut output)
+            .unwrap();
+        assert_eq!(output, vec![2, 4, 6, 0]);
+    }
+
+    #[test]
+    fn test_run_with_closures() {
+        let mut next = 0;
+        let mut total = 0;
+        doubler()
+            .run_with(
+                InputFn(|| {
+                    next += 1;
+                    Some(if next > 4 { 0 } else { next })
+                }),
+                OutputFn(|value| total += value),
+            )
+            .unwrap();
+        assert_eq!(total, 20);
+    }
+
+    #[test]
+    fn test_run_with_reader_and_writer() {
+        let mut written = vec![];
+        doubler()
+            .run_with(
+                ReaderInput::new("5, 6\n7\n0\n".as_bytes()),
+                WriterOutput::new(&mut written),
+            )
+            .unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), "10\n12\n14\n0\n");
+    }
+
+    #[test]
+    fn test_run_with_running_out() {
+        let mut cpu = doubler();
+        let mut output = vec![];
+        let error = cpu
+            .run_with(ReaderInput::new("5 six\n".as_bytes()), &mut output)
+            .unwrap_err();
+        assert_eq!(error.kind, IntCodeErrorKind::InputUnderflow);
+        assert_eq!(output, vec![10]);
+        // It went to the sink, so the cpu didn't keep a copy
+        assert!(cpu.output.is_empty());
+    }
+
+    #[test]
+    fn test_run_with_channels() {
+        // Two doublers in a row, each on its own thread
+        let (to_first, first_input) = mpsc::channel();
+        let (to_second, second_input) = mpsc::channel();
+        let (to_us, results) = mpsc::channel();
+        thread::scope(|scope| {
+            scope.spawn(|| doubler().run_with(first_input, to_second).unwrap());
+            scope.spawn(|| doubler().run_with(second_input, to_us).unwrap());
+            for value in [1, 2, 3, 0] {
+                to_first.send(value).unwrap();
+            }
+        });
+        assert_eq!(results.iter().collect::<Vec<_>>(), vec![4, 8, 12, 0]);
+    }
+}
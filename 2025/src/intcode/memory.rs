@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::ops::{Index, IndexMut};
+use std::sync::Arc;
+
+/* How far past the end of the dense memory a write can land before we stop growing the Vec and
+ * store it in the sparse map instead.  Programs tend to use scratch space just past their own code,
+ * but occasionally poke at an address miles away, and we don't want to allocate gigabytes for that */
+pub(crate) const MAX_DENSE_GROWTH: usize = 4096;
+
+static ZERO: i64 = 0;
+
+#[derive(Debug, Clone, Default)]
+struct Cells {
+    dense: Vec<i64>,
+    sparse: HashMap<usize, i64>,
+}
+
+/* Memory we own outright can be written by just indexing the Vec.  Once it's been shared with a
+ * snapshot neither side can write to it, so the first write takes a copy (or takes it back, if
+ * nobody else is still holding it) and from then on it's owned again */
+#[derive(Debug, Clone)]
+enum Storage {
+    Owned(Cells),
+    Shared(Arc<Cells>),
+}
+
+/* Intcode memory.  The loaded program lives in a plain Vec so the common case is just an index,
+ * anything written far beyond it ends up in a HashMap.  Reads of addresses that have never been
+ * written return 0.
+ * Cloning copies the memory, but share() hands it over to an Arc first, so that it and all its
+ * clones use the same cells until one of them writes.  That's what snapshots use, so forking a CPU
+ * to try something out costs next to nothing */
+#[derive(Debug, Clone)]
+pub struct Memory {
+    storage: Storage,
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Memory::new(vec![])
+    }
+}
+
+impl Memory {
+    pub fn new(program: Vec<i64>) -> Self {
+        Memory {
+            storage: Storage::Owned(Cells {
+                dense: program,
+                sparse: HashMap::new(),
+            }),
+        }
+    }
+
+    // Make clones of this memory share it rather than copy it, until somebody writes
+    pub fn share(&mut self) {
+        if let Storage::Owned(cells) = &mut self.storage {
+            self.storage = Storage::Shared(Arc::new(std::mem::take(cells)));
+        }
+    }
+
+    pub fn is_shared(&self) -> bool {
+        matches!(self.storage, Storage::Shared(_))
+    }
+
+    fn cells(&self) -> &Cells {
+        match &self.storage {
+            Storage::Owned(cells) => cells,
+            Storage::Shared(cells) => cells,
+        }
+    }
+
+    fn cells_mut(&mut self) -> &mut Cells {
+        if let Storage::Shared(cells) = &self.storage {
+            let cells = Arc::clone(cells);
+            // Drop our own reference first, so if we were the last one holding it we get it back for free
+            self.storage = Storage::Owned(Cells::default());
+            self.storage = Storage::Owned(Arc::unwrap_or_clone(cells));
+        }
+        match &mut self.storage {
+            Storage::Owned(cells) => cells,
+            Storage::Shared(_) => unreachable!("Memory was just made unique"),
+        }
+    }
+
+    // The loaded program plus anything written near it
+    pub fn dense(&self) -> &[i64] {
+        &self.cells().dense
+    }
+
+    // Anything written a long way past the end of the dense memory
+    pub fn sparse(&self) -> impl Iterator<Item = (usize, i64)> + '_ {
+        self.cells().sparse.iter().map(|(a, v)| (*a, *v))
+    }
+
+    pub fn get(&self, address: usize) -> i64 {
+        let cells = self.cells();
+        match cells.dense.get(address) {
+            Some(value) => *value,
+            None => cells.sparse.get(&address).copied().unwrap_or(0),
+        }
+    }
+
+    pub fn set(&mut self, address: usize, value: i64) {
+        *self.slot(address) = value;
+    }
+
+    // Find the cell for an address, growing the dense memory if it's close enough to the end
+    fn slot(&mut self, address: usize) -> &mut i64 {
+        let cells = self.cells_mut();
+        if address >= cells.dense.len() && address < cells.dense.len() + MAX_DENSE_GROWTH {
+            let start = cells.dense.len();
+            cells.dense.resize(address + 1, 0);
+            // Anything we've just pulled into the dense memory might already be in the sparse map
+            if !cells.sparse.is_empty() {
+                for (a, cell) in cells.dense.iter_mut().enumerate().skip(start) {
+                    if let Some(value) = cells.sparse.remove(&a) {
+                        *cell = value;
+                    }
+                }
+            }
+        }
+        if address < cells.dense.len() {
+            &mut cells.dense[address]
+        } else {
+            cells.sparse.entry(address).or_insert(0)
+        }
+    }
+}
+
+impl Index<usize> for Memory {
+    type Output = i64;
+    fn index(&self, address: usize) -> &i64 {
+        let cells = self.cells();
+        match cells.dense.get(address) {
+            Some(value) => value,
+            None => cells.sparse.get(&address).unwrap_or(&ZERO),
+        }
+    }
+}
+
+impl IndexMut<usize> for Memory {
+    fn index_mut(&mut self, address: usize) -> &mut i64 {
+        self.slot(address)
+    }
+}
+
+impl PartialEq<Vec<i64>> for Memory {
+    fn eq(&self, other: &Vec<i64>) -> bool {
+        let cells = self.cells();
+        cells.sparse.values().all(|v| *v == 0)
+            && cells.dense.len() >= other.len()
+            && cells.dense[..other.len()] == other[..]
+            && cells.dense[other.len()..].iter().all(|v| *v == 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::memory::*;
+
+    #[test]
+    fn test_memory_reads_past_end_are_zero() {
+        let memory = Memory::new(vec![1, 2, 3]);
+        assert_eq!(memory.get(1), 2);
+        assert_eq!(memory.get(3), 0);
+        assert_eq!(memory[1_000_000], 0);
+        assert_eq!(memory.dense().len(), 3);
+    }
+
+    #[test]
+    fn test_memory_writes_grow() {
+        let mut memory = Memory::new(vec![1, 2, 3]);
+        memory.set(10, 99);
+        assert_eq!(memory.dense().len(), 11);
+        assert_eq!(memory[10], 99);
+        assert_eq!(memory[7], 0);
+        memory[11] += 5;
+        assert_eq!(memory[11], 5);
+        assert_eq!(memory, vec![1, 2, 3, 0, 0, 0, 0, 0, 0, 0, 99, 5]);
+    }
+
+    #[test]
+    fn test_memory_far_writes_are_sparse() {
+        let mut memory = Memory::new(vec![1, 2, 3]);
+        memory.set(1_000_000_000, 1234567890123);
+        assert_eq!(memory.dense().len(), 3);
+        assert_eq!(memory[1_000_000_000], 1234567890123);
+        assert_eq!(memory[999_999_999], 0);
+    }
+
+    #[test]
+    fn test_memory_sparse_moves_into_dense() {
+        let mut memory = Memory::new(vec![1, 2, 3]);
+        memory.set(5000, 7);
+        memory.set(4000, 6);
+        assert_eq!(memory.dense().len(), 4001);
+        memory.set(5000 + 1, 8);
+        assert_eq!(memory.dense().len(), 5002);
+        assert_eq!(memory[5000], 7);
+        assert_eq!(memory[5001], 8);
+    }
+
+    #[test]
+    fn test_memory_copy_on_write() {
+        let mut memory = Memory::new(vec![1, 2, 3]);
+        memory.set(1_000_000, 4);
+        // A plain clone is a copy of its own
+        assert!(!memory.clone().is_shared());
+        memory.share();
+        let mut copy = memory.clone();
+        assert!(copy.is_shared());
+        copy.set(0, 10);
+        assert!(!copy.is_shared());
+        assert!(memory.is_shared());
+        copy.set(1_000_000, 40);
+        assert_eq!(memory[0], 1);
+        assert_eq!(memory[1_000_000], 4);
+        assert_eq!(copy[0], 10);
+        assert_eq!(copy[1_000_000], 40);
+        // The copy has gone its own way, so the original gets the shared cells back
+        memory.set(2, 30);
+        assert!(!memory.is_shared());
+        assert_eq!(memory.dense(), [1, 2, 30]);
+    }
+}
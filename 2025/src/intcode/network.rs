@@ -0,0 +1,356 @@
+use crate::intcode::intcodecpu::{IntCodeCPU, IntCodeError, RunStatus};
+use std::collections::VecDeque;
+use std::fmt;
+
+// Packets sent here go to the NAT rather than a CPU
+pub const NAT_ADDRESS: usize = 255;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Packet {
+    // Which round of the scheduler the packet was sent in
+    pub round: usize,
+    pub from: usize,
+    pub to: usize,
+    pub x: i64,
+    pub y: i64,
+}
+
+impl fmt::Display for Packet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} -> {}: {} {}",
+            self.round, self.from, self.to, self.x, self.y
+        )
+    }
+}
+
+impl Packet {
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.replace("->", " ").replace(':', " ");
+        let numbers: Vec<i64> = line
+            .split_whitespace()
+            .map(|n| n.parse().ok())
+            .collect::<Option<_>>()?;
+        let [round, from, to, x, y] = numbers[..] else {
+            return None;
+        };
+        Some(Packet {
+            round: usize::try_from(round).ok()?,
+            from: usize::try_from(from).ok()?,
+            to: usize::try_from(to).ok()?,
+            x,
+            y,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    Continue,
+    Stop,
+}
+
+/* Solvers watch the traffic through one of these.  Every packet goes past on_packet, including the
+ * ones the NAT sends, which come from NAT_ADDRESS */
+pub trait NetworkHook {
+    fn on_packet(&mut self, _packet: &Packet) -> Flow {
+        Flow::Continue
+    }
+    fn on_idle(&mut self, _round: usize) -> Flow {
+        Flow::Continue
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkError {
+    Cpu(usize, IntCodeError),
+    // Everyone's idle and the NAT has nothing to wake them up with
+    Deadlock,
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkError::Cpu(address, error) => write!(f, "cpu {}: {}", address, error),
+            NetworkError::Deadlock => write!(f, "network is idle and the nat has nothing to send"),
+        }
+    }
+}
+
+impl std::error::Error for NetworkError {}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PacketLog {
+    pub packets: Vec<Packet>,
+}
+
+impl PacketLog {
+    pub fn parse(text: &str) -> Option<Self> {
+        Some(PacketLog {
+            packets: text.lines().map(Packet::parse).collect::<Option<_>>()?,
+        })
+    }
+
+    // Play recorded traffic back through a hook, without having to run any CPUs
+    pub fn replay(&self, hook: &mut impl NetworkHook) {
+        for packet in &self.packets {
+            if hook.on_packet(packet) == Flow::Stop {
+                break;
+            }
+        }
+    }
+}
+
+impl fmt::Display for PacketLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for packet in &self.packets {
+            writeln!(f, "{}", packet)?;
+        }
+        Ok(())
+    }
+}
+
+/* A set of CPUs that each boot with their own address as their first input, and then send each other
+ * packets as output triples of address, x and y.  Reading input when no packet is waiting gives -1.
+ * We run them round robin, each until it wants input, so a run is always the same */
+pub struct Network {
+    cpus: Vec<IntCodeCPU>,
+    queues: Vec<VecDeque<(i64, i64)>>,
+    // Output values we've had from each CPU that don't make a full packet yet
+    partial: Vec<Vec<i64>>,
+    nat: Option<(i64, i64)>,
+    pub log: PacketLog,
+    round: usize,
+}
+
+impl Network {
+    pub fn new(count: usize, program: &[i64]) -> Self {
+        Network {
+            cpus: (0..count)
+                .map(|address| IntCodeCPU::new_with_io(program.to_vec(), vec![address as i64]))
+                .collect(),
+            queues: vec![VecDeque::new(); count],
+            partial: vec![vec![]; count],
+            nat: None,
+            log: PacketLog::default(),
+            round: 0,
+        }
+    }
+
+    // Route a packet to a CPU or the NAT, anything addressed elsewhere is logged and dropped
+    fn send(&mut self, packet: Packet, hook: &mut impl NetworkHook) -> Flow {
+        self.log.packets.push(packet);
+        if packet.to == NAT_ADDRESS {
+            self.nat = Some((packet.x, packet.y));
+        } else if let Some(queue) = self.queues.get_mut(packet.to) {
+            queue.push_back((packet.x, packet.y));
+        }
+        hook.on_packet(&packet)
+    }
+
+    // Run every CPU once, returning whether anything was sent or received
+    fn run_round(&mut self, hook: &mut impl NetworkHook) -> Result<(bool, Flow), NetworkError> {
+        let mut busy = false;
+        for address in 0..self.cpus.len() {
+            let cpu = &mut self.cpus[address];
+            loop {
+                match cpu.resume().map_err(|e| NetworkError::Cpu(address, e))? {
+                    RunStatus::Output(value) => self.partial[address].push(value),
+                    RunStatus::NeedsInput | RunStatus::Halted => break,
+                    RunStatus::Running => {}
+                }
+            }
+            while self.partial[address].len() >= 3 {
+                let values: Vec<i64> = self.partial[address].drain(..3).collect();
+                busy = true;
+                let packet = Packet {
+                    round: self.round,
+                    from: address,
+                    to: usize::try_from(values[0]).unwrap_or(usize::MAX),
+                    x: values[1],
+                    y: values[2],
+                };
+                if self.send(packet, hook) == Flow::Stop {
+                    return Ok((busy, Flow::Stop));
+                }
+            }
+            // Now it's waiting, give it its packets or tell it there aren't any
+            let cpu = &mut self.cpus[address];
+            if self.queues[address].is_empty() {
+                cpu.push_input(-1);
+            } else {
+                busy = true;
+                for (x, y) in self.queues[address].drain(..) {
+                    cpu.push_input(x);
+                    cpu.push_input(y);
+                }
+            }
+        }
+        Ok((busy, Flow::Continue))
+    }
+
+    /* Keep running rounds until the hook tells us to stop.  When a whole round goes by with nothing sent
+     * and nobody with a packet waiting, the network is idle and the NAT resends its last packet to 0 */
+    pub fn run(&mut self, hook: &mut impl NetworkHook) -> Result<(), NetworkError> {
+        loop {
+            let (busy, flow) = self.run_round(hook)?;
+            if flow == Flow::Stop {
+                return Ok(());
+            }
+            // Once everyone has halted there's nobody left for the NAT to wake up
+            if self.cpus.iter().all(|cpu| !cpu.running) {
+                return Err(NetworkError::Deadlock);
+            }
+            if !busy {
+                if hook.on_idle(self.round) == Flow::Stop {
+                    return Ok(());
+                }
+                let (x, y) = self.nat.ok_or(NetworkError::Deadlock)?;
+                let packet = Packet {
+                    round: self.round,
+                    from: NAT_ADDRESS,
+                    to: 0,
+                    x,
+                    y,
+                };
+                if self.send(packet, hook) == Flow::Stop {
+                    return Ok(());
+                }
+            }
+            self.round += 1;
+        }
+    }
+}
+
+// Day 23 part 1 watches for the first packet to the NAT
+#[derive(Default)]
+pub struct FirstNatPacket {
+    pub y: Option<i64>,
+}
+
+impl NetworkHook for FirstNatPacket {
+    fn on_packet(&mut self, packet: &Packet) -> Flow {
+        if packet.to == NAT_ADDRESS {
+            self.y = Some(packet.y);
+            Flow::Stop
+        } else {
+            Flow::Continue
+        }
+    }
+}
+
+// Day 23 part 2 wants the first y the NAT delivers twice in a row
+#[derive(Default)]
+pub struct RepeatedNatY {
+    last: Option<i64>,
+    pub y: Option<i64>,
+}
+
+impl NetworkHook for RepeatedNatY {
+    fn on_packet(&mut self, packet: &Packet) -> Flow {
+        if packet.from != NAT_ADDRESS {
+            return Flow::Continue;
+        }
+        if self.last == Some(packet.y) {
+            self.y = Some(packet.y);
+            return Flow::Stop;
+        }
+        self.last = Some(packet.y);
+        Flow::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::assembler::assemble;
+    use crate::intcode::network::*;
+
+    /* Node 0 kicks things off by sending to 1, and every node passes what it gets on to the next with x
+     * incremented, until the last one sends it to the NAT */
+    fn relay() -> Vec<i64> {
+        assemble(
+            "       IN -> [addr]
+                    JT [addr], #loop
+                    OUT #1
+                    OUT #10
+                    OUT #20
+            loop:   IN -> [x]
+                    EQ [x], #-1 -> [tmp]
+                    JT [tmp], #loop
+                    IN -> [y]
+                    ADD [addr], #1 -> [next]
+                    EQ [next], #3 -> [tmp]
+                    JF [tmp], #send
+                    ADD #255, #0 -> [next]
+            send:   OUT [next]
+                    ADD [x], #1 -> [x]
+                    OUT [x]
+                    OUT [y]
+                    JT #1, #loop
+            addr:   DATA 0
+            x:      DATA 0
+            y:      DATA 0
+            tmp:    DATA 0
+            next:   DATA 0",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_network_first_nat_packet() {
+        let mut network = Network::new(3, &relay());
+        let mut hook = FirstNatPacket::default();
+        network.run(&mut hook).unwrap();
+        assert_eq!(hook.y, Some(20));
+        let route: Vec<(usize, usize, i64)> = network
+            .log
+            .packets
+            .iter()
+            .map(|p| (p.from, p.to, p.x))
+            .collect();
+        assert_eq!(route, vec![(0, 1, 10), (1, 2, 11), (2, 255, 12)]);
+    }
+
+    #[test]
+    fn test_network_nat_wakes_idle_network() {
+        let mut network = Network::new(3, &relay());
+        let mut hook = RepeatedNatY::default();
+        network.run(&mut hook).unwrap();
+        assert_eq!(hook.y, Some(20));
+        let nat: Vec<i64> = network
+            .log
+            .packets
+            .iter()
+            .filter(|p| p.from == NAT_ADDRESS)
+            .map(|p| p.x)
+            .collect();
+        assert_eq!(nat, vec![12, 15]);
+    }
+
+    #[test]
+    fn test_network_deadlock() {
+        // Nobody ever sends anything, so there's nothing for the NAT to send
+        let mut network = Network::new(2, &[3, 5, 1105, 1, 0, 0]);
+        assert_eq!(
+            network.run(&mut FirstNatPacket::default()),
+            Err(NetworkError::Deadlock)
+        );
+    }
+
+    #[test]
+    fn test_packet_log_replay() {
+        let mut network = Network::new(3, &relay());
+        network.run(&mut RepeatedNatY::default()).unwrap();
+        let text = network.log.to_string();
+        assert!(text.starts_with("0 0 -> 1: 10 20\n"));
+        let log = PacketLog::parse(&text).unwrap();
+        assert_eq!(log, network.log);
+        let mut first = FirstNatPacket::default();
+        log.replay(&mut first);
+        assert_eq!(first.y, Some(20));
+        let mut repeated = RepeatedNatY::default();
+        log.replay(&mut repeated);
+        assert_eq!(repeated.y, Some(20));
+    }
+}
@@ -0,0 +1,191 @@
+use crate::intcode::decode::{Instruction, Operation};
+use crate::intcode::intcodecpu::IntCodeCPU;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+// How many of the busiest addresses the report lists
+const HOT_ADDRESSES: usize = 10;
+
+/* Counts of everything the CPU did while profiling was switched on.  Reads only count values fetched
+ * from memory by position or relative parameters, not the instruction words themselves, which are
+ * already covered by the executed counts */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profile {
+    pub cycles: u64,
+    pub operations: HashMap<Operation, u64>,
+    pub executed: HashMap<usize, u64>,
+    pub reads: HashMap<usize, u64>,
+    pub writes: HashMap<usize, u64>,
+    // How long each executed instruction was, so we know which words are code
+    sizes: HashMap<usize, usize>,
+}
+
+// Highest count first, and by address when the counts are the same so the report is stable
+fn busiest<K: Copy + Ord>(counts: &HashMap<K, u64>) -> Vec<(K, u64)> {
+    let mut counts: Vec<(K, u64)> = counts.iter().map(|(k, v)| (*k, *v)).collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    counts
+}
+
+fn json_counts<K: Copy + Ord + fmt::Display>(counts: &HashMap<K, u64>) -> String {
+    let mut counts: Vec<(K, u64)> = counts.iter().map(|(k, v)| (*k, *v)).collect();
+    counts.sort();
+    let fields: Vec<String> = counts
+        .iter()
+        .map(|(k, v)| format!("\"{}\":{}", k, v))
+        .collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+impl Profile {
+    pub(crate) fn record(&mut self, address: usize, instruction: &Instruction) {
+        self.cycles += 1;
+        *self.operations.entry(instruction.operation).or_default() += 1;
+        let count = self.executed.entry(address).or_default();
+        if *count == 0 {
+            self.sizes.insert(address, instruction.size);
+        }
+        *count += 1;
+    }
+
+    pub(crate) fn read(&mut self, address: usize) {
+        *self.reads.entry(address).or_default() += 1;
+    }
+
+    pub(crate) fn write(&mut self, address: usize) {
+        *self.writes.entry(address).or_default() += 1;
+    }
+
+    /* Addresses that were both run as code and written to.  That's usually a program patching its own
+     * parameters, but it'll also catch one that was overwritten with entirely new code */
+    pub fn self_modified(&self) -> BTreeSet<usize> {
+        self.sizes
+            .iter()
+            .flat_map(|(&address, &size)| address..address + size)
+            .filter(|address| self.writes.contains_key(address))
+            .collect()
+    }
+
+    pub fn to_json(&self) -> String {
+        let operations: HashMap<&str, u64> = self
+            .operations
+            .iter()
+            .map(|(op, count)| (op.mnemonic(), *count))
+            .collect();
+        let self_modified: Vec<String> = self
+            .self_modified()
+            .iter()
+            .map(|address| address.to_string())
+            .collect();
+        format!(
+            "{{\"cycles\":{},\"operations\":{},\"executed\":{},\"reads\":{},\"writes\":{},\"self_modified\":[{}]}}",
+            self.cycles,
+            json_counts(&operations),
+            json_counts(&self.executed),
+            json_counts(&self.reads),
+            json_counts(&self.writes),
+            self_modified.join(",")
+        )
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "cycles: {}", self.cycles)?;
+        let total = self.cycles.max(1) as f64;
+        writeln!(f, "operations:")?;
+        for (operation, count) in busiest(&self.operations) {
+            writeln!(
+                f,
+                "  {:<4} {:>12} {:>6.1}%",
+                operation.mnemonic(),
+                count,
+                count as f64 * 100.0 / total
+            )?;
+        }
+        for (title, counts) in [
+            ("executed", &self.executed),
+            ("read", &self.reads),
+            ("written", &self.writes),
+        ] {
+            writeln!(f, "most {}:", title)?;
+            for (address, count) in busiest(counts).into_iter().take(HOT_ADDRESSES) {
+                writeln!(f, "  {:04} {:>12}", address, count)?;
+            }
+        }
+        let self_modified = self.self_modified();
+        if !self_modified.is_empty() {
+            let addresses: Vec<String> =
+                self_modified.iter().map(|a| format!("{:04}", a)).collect();
+            writeln!(f, "self modified: {}", addresses.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl IntCodeCPU {
+    // Start counting from here, throwing away anything we'd counted before
+    pub fn enable_profiling(&mut self) {
+        self.profile = Some(Box::default());
+    }
+
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::parse_program;
+    use crate::intcode::profile::*;
+
+    #[test]
+    fn test_profile_counts() {
+        // Day 2's example, which adds, multiplies and halts
+        let mut cpu = IntCodeCPU::new(parse_program("1,9,10,3,2,3,11,0,99,30,40,50"));
+        assert_eq!(cpu.profile(), None);
+        cpu.enable_profiling();
+        cpu.run().unwrap();
+        let profile = cpu.profile().unwrap();
+        assert_eq!(profile.cycles, 3);
+        assert_eq!(profile.operations[&Operation::Add], 1);
+        assert_eq!(profile.operations[&Operation::Halt], 1);
+        assert_eq!(
+            profile.executed.keys().copied().collect::<BTreeSet<_>>(),
+            BTreeSet::from([0, 4, 8])
+        );
+        assert_eq!(profile.reads[&3], 1);
+        assert_eq!(profile.reads[&9], 1);
+        assert_eq!(profile.writes[&0], 1);
+        // The add writes over its own target, and the multiply writes over the add
+        assert_eq!(profile.self_modified(), BTreeSet::from([0, 3]));
+    }
+
+    #[test]
+    fn test_profile_loop() {
+        // Day 9's quine, which copies itself out one value at a time
+        let quine = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        let mut cpu = IntCodeCPU::new(parse_program(quine));
+        cpu.enable_profiling();
+        cpu.run().unwrap();
+        let profile = cpu.profile().unwrap();
+        assert_eq!(profile.executed[&2], 16);
+        assert_eq!(profile.operations[&Operation::Output], 16);
+        assert_eq!(profile.writes[&100], 16);
+        let report = profile.to_string();
+        assert!(report.starts_with("cycles: 81\noperations:\n  ADD            16   19.8%\n"));
+        assert!(report.contains("most written:\n  0100           16\n  0101           16\n"));
+        assert!(!report.contains("self modified"));
+    }
+
+    #[test]
+    fn test_profile_json() {
+        let mut cpu = IntCodeCPU::new(parse_program("1101,2,3,5,99,0"));
+        cpu.enable_profiling();
+        cpu.run().unwrap();
+        assert_eq!(
+            cpu.profile().unwrap().to_json(),
+            "{\"cycles\":2,\"operations\":{\"ADD\":1,\"HLT\":1},\"executed\":{\"0\":1,\"4\":1},\"reads\":{},\"writes\":{\"5\":1},\"self_modified\":[]}"
+        );
+    }
+}
@@ -0,0 +1,382 @@
+use crate::intcode::intcodecpu::{IntCodeCPU, IntCodeError, RunStatus};
+use crate::utils::Coordinate;
+use std::collections::{HashMap, VecDeque};
+
+/* Which way the robot is pointing.  y goes down the screen, the same as it does for a Grid, so up is
+ * negative y */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Right,
+        Direction::Down,
+        Direction::Left,
+    ];
+
+    pub fn offset(&self) -> Coordinate {
+        match self {
+            Direction::Up => Coordinate::new(0, -1),
+            Direction::Right => Coordinate::new(1, 0),
+            Direction::Down => Coordinate::new(0, 1),
+            Direction::Left => Coordinate::new(-1, 0),
+        }
+    }
+
+    pub fn turn_left(&self) -> Self {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Right => Direction::Up,
+            Direction::Down => Direction::Right,
+            Direction::Left => Direction::Down,
+        }
+    }
+
+    pub fn turn_right(&self) -> Self {
+        self.turn_left().turn_left().turn_left()
+    }
+
+    pub fn reverse(&self) -> Self {
+        self.turn_left().turn_left()
+    }
+
+    // The day 15 droid takes north, south, west and east as 1 to 4
+    pub fn movement_command(&self) -> i64 {
+        match self {
+            Direction::Up => 1,
+            Direction::Down => 2,
+            Direction::Left => 3,
+            Direction::Right => 4,
+        }
+    }
+
+    // How the day 17 camera draws the robot
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            '^' => Some(Direction::Up),
+            '>' => Some(Direction::Right),
+            'v' => Some(Direction::Down),
+            '<' => Some(Direction::Left),
+            _ => None,
+        }
+    }
+}
+
+// What the day 15 droid reports after each move
+pub const WALL: i64 = 0;
+pub const OPEN: i64 = 1;
+pub const OXYGEN: i64 = 2;
+
+/* A robot with an intcode brain, moving around a sparse map.  What the map holds depends on the
+ * puzzle, panel colours for the day 11 hull painter, move statuses for the day 15 droid and camera
+ * characters for the day 17 scaffold, but they all start at (0,0) facing up */
+pub struct Robot {
+    pub cpu: IntCodeCPU,
+    pub position: Coordinate,
+    pub facing: Direction,
+    pub map: HashMap<Coordinate, i64>,
+}
+
+impl Robot {
+    pub fn new(program: Vec<i64>) -> Self {
+        Robot {
+            cpu: IntCodeCPU::new(program),
+            position: Coordinate::new(0, 0),
+            facing: Direction::Up,
+            map: HashMap::new(),
+        }
+    }
+
+    // Run until the brain gives us a value, None means it's halted, and it's a bug if it wants input
+    fn next_output(&mut self) -> Result<Option<i64>, IntCodeError> {
+        loop {
+            match self.cpu.resume()? {
+                RunStatus::Output(value) => return Ok(Some(value)),
+                RunStatus::Halted => return Ok(None),
+                RunStatus::NeedsInput => return Err(self.cpu.input_underflow()),
+                RunStatus::Running => {}
+            }
+        }
+    }
+
+    /* Day 11.  The brain reads the colour of the panel we're on, then tells us what colour to paint it
+     * and which way to turn (0 for left, 1 for right), and we move forward one panel.  Only panels that
+     * have been painted end up in the map, so its size is how many we painted */
+    pub fn paint(&mut self) -> Result<(), IntCodeError> {
+        loop {
+            let colour = self.map.get(&self.position).copied().unwrap_or(0);
+            self.cpu.push_input(colour);
+            let Some(colour) = self.next_output()? else {
+                return Ok(());
+            };
+            let Some(turn) = self.next_output()? else {
+                return Ok(());
+            };
+            self.map.insert(self.position, colour);
+            self.facing = if turn == 0 {
+                self.facing.turn_left()
+            } else {
+                self.facing.turn_right()
+            };
+            self.position = self.position + self.facing.offset();
+        }
+    }
+
+    /* Day 15.  Try to move one step, recording what's there, and only actually moving if it's not a
+     * wall.  Returns the droid's status, WALL, OPEN or OXYGEN */
+    pub fn step(&mut self, direction: Direction) -> Result<i64, IntCodeError> {
+        self.cpu.push_input(direction.movement_command());
+        let status = self.next_output()?.unwrap_or(WALL);
+        let target = self.position + direction.offset();
+        self.map.insert(target, status);
+        if status != WALL {
+            self.position = target;
+        }
+        Ok(status)
+    }
+
+    /* Walk everywhere we can reach, backtracking out of dead ends, so the map ends up covering the whole
+     * area.  The droid finishes back where it started */
+    pub fn explore(&mut self) -> Result<(), IntCodeError> {
+        self.map.entry(self.position).or_insert(OPEN);
+        for direction in Direction::ALL {
+            if self.map.contains_key(&(self.position + direction.offset())) {
+                continue;
+            }
+            if self.step(direction)? != WALL {
+                self.explore()?;
+                self.step(direction.reverse())?;
+            }
+        }
+        Ok(())
+    }
+
+    // How many steps it takes to get everywhere reachable from start, only going through open cells
+    pub fn distances(
+        &self,
+        start: Coordinate,
+        open: impl Fn(i64) -> bool,
+    ) -> HashMap<Coordinate, usize> {
+        let mut distances = HashMap::from([(start, 0)]);
+        let mut queue = VecDeque::from([start]);
+        while let Some(current) = queue.pop_front() {
+            let distance = distances[&current];
+            for direction in Direction::ALL {
+                let next = current + direction.offset();
+                if !distances.contains_key(&next) && self.map.get(&next).is_some_and(|v| open(*v)) {
+                    distances.insert(next, distance + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+        distances
+    }
+
+    /* Day 17.  The camera outputs a picture as lines of text, which we read into the map as characters,
+     * with the top left at (0,0).  The picture ends with a blank line, or when the program stops */
+    pub fn scan(&mut self) -> Result<(), IntCodeError> {
+        let mut cursor = Coordinate::new(0, 0);
+        loop {
+            let value = match self.cpu.resume()? {
+                RunStatus::Output(value) => value,
+                RunStatus::Running => continue,
+                RunStatus::NeedsInput | RunStatus::Halted => return Ok(()),
+            };
+            if value == '\n' as i64 {
+                if cursor.x == 0 {
+                    return Ok(());
+                }
+                cursor = Coordinate::new(0, cursor.y + 1);
+                continue;
+            }
+            if let Some(direction) = u8::try_from(value)
+                .ok()
+                .and_then(|c| Direction::from_char(c as char))
+            {
+                self.position = cursor;
+                self.facing = direction;
+            }
+            self.map.insert(cursor, value);
+            cursor = cursor + Direction::Right.offset();
+        }
+    }
+
+    // Draw everything in the map, trimming the blank space off the right of each line
+    pub fn render(&self, draw: impl Fn(Option<i64>) -> char) -> String {
+        if self.map.is_empty() {
+            return String::new();
+        }
+        let (min_x, max_x) = (
+            self.map.keys().map(|c| c.x).min().unwrap(),
+            self.map.keys().map(|c| c.x).max().unwrap(),
+        );
+        let (min_y, max_y) = (
+            self.map.keys().map(|c| c.y).min().unwrap(),
+            self.map.keys().map(|c| c.y).max().unwrap(),
+        );
+        (min_y..=max_y)
+            .map(|y| {
+                let line: String = (min_x..=max_x)
+                    .map(|x| draw(self.map.get(&Coordinate::new(x, y)).copied()))
+                    .collect();
+                line.trim_end().to_string() + "\n"
+            })
+            .collect()
+    }
+
+    // The day 11 registration identifier, white panels as # on a blank background
+    pub fn render_panels(&self) -> String {
+        self.render(|colour| if colour == Some(1) { '#' } else { ' ' })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::assembler::assemble;
+    use crate::intcode::robot::*;
+
+    // A brain that ignores what it sees and just gives back a fixed set of commands
+    fn scripted(commands: &[(i64, i64)]) -> Vec<i64> {
+        let mut source: String = commands
+            .iter()
+            .map(|(colour, turn)| format!("IN -> [seen]\nOUT #{}\nOUT #{}\n", colour, turn))
+            .collect();
+        source += "HLT\nseen: DATA 0";
+        assemble(&source).unwrap()
+    }
+
+    #[test]
+    fn test_direction() {
+        assert_eq!(Direction::Up.turn_left(), Direction::Left);
+        assert_eq!(Direction::Up.turn_right(), Direction::Right);
+        assert_eq!(Direction::Left.reverse(), Direction::Right);
+        assert_eq!(
+            Coordinate::new(2, 2) + Direction::Down.offset(),
+            Coordinate::new(2, 3)
+        );
+        assert_eq!(Direction::from_char('v'), Some(Direction::Down));
+    }
+
+    #[test]
+    fn test_paint() {
+        // The walk through in day 11's description
+        let mut robot = Robot::new(scripted(&[
+            (1, 0),
+            (0, 0),
+            (1, 0),
+            (1, 0),
+            (0, 1),
+            (1, 0),
+            (1, 0),
+        ]));
+        robot.paint().unwrap();
+        assert_eq!(robot.map.len(), 6);
+        assert_eq!(robot.position, Coordinate::new(0, -1));
+        assert_eq!(robot.facing, Direction::Left);
+        assert_eq!(robot.render_panels(), "  #\n  #\n##\n");
+    }
+
+    /* A droid in a little maze, stored a row at a time with 0 for walls, 1 for open and 2 for the
+     * oxygen.  It starts on the bottom row, four moves from the oxygen */
+    fn maze() -> Vec<i64> {
+        assemble(
+            "   loop:   IN -> [cmd]
+                        EQ [cmd], #1 -> [t]
+                        JF [t], #south
+                        ADD [p], #-5 -> [np]
+                        JT #1, #look
+            south:      EQ [cmd], #2 -> [t]
+                        JF [t], #west
+                        ADD [p], #5 -> [np]
+                        JT #1, #look
+            west:       EQ [cmd], #3 -> [t]
+                        JF [t], #east
+                        ADD [p], #-1 -> [np]
+                        JT #1, #look
+            east:       ADD [p], #1 -> [np]
+            look:       ADD [np], #maze -> [addr]
+                        ARB [addr]
+                        ADD [rb], #0 -> [status]
+                        MUL [addr], #-1 -> [addr]
+                        ARB [addr]
+                        OUT [status]
+                        JF [status], #loop
+                        ADD [np], #0 -> [p]
+                        JT #1, #loop
+            cmd:        DATA 0
+            t:          DATA 0
+            p:          DATA 16
+            np:         DATA 0
+            addr:       DATA 0
+            status:     DATA 0
+            maze:       DATA 0, 0, 0, 0, 0
+                        DATA 0, 1, 1, 2, 0
+                        DATA 0, 1, 0, 0, 0
+                        DATA 0, 1, 1, 1, 0
+                        DATA 0, 0, 0, 0, 0",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_step() {
+        let mut robot = Robot::new(maze());
+        assert_eq!(robot.step(Direction::Down).unwrap(), WALL);
+        assert_eq!(robot.position, Coordinate::new(0, 0));
+        assert_eq!(robot.step(Direction::Up).unwrap(), OPEN);
+        assert_eq!(robot.position, Coordinate::new(0, -1));
+        assert_eq!(robot.map[&Coordinate::new(0, 1)], WALL);
+    }
+
+    #[test]
+    fn test_explore() {
+        let mut robot = Robot::new(maze());
+        robot.explore().unwrap();
+        assert_eq!(robot.position, Coordinate::new(0, 0));
+        let oxygen = Coordinate::new(2, -2);
+        assert_eq!(robot.map[&oxygen], OXYGEN);
+        let distances = robot.distances(Coordinate::new(0, 0), |v| v != WALL);
+        assert_eq!(distances[&oxygen], 4);
+        assert_eq!(
+            robot.distances(oxygen, |v| v != WALL).values().max(),
+            Some(&6)
+        );
+        assert_eq!(
+            robot.render(|v| match v {
+                Some(WALL) => '#',
+                Some(OXYGEN) => 'O',
+                Some(_) => '.',
+                None => ' ',
+            }),
+            // Exploring never looks at the corners, so they stay blank
+            " ###\n#..O#\n#.##\n#...#\n ###\n"
+        );
+    }
+
+    #[test]
+    fn test_scan() {
+        let picture = "..#..\n..#..\n##v##\n\n";
+        let mut source: String = picture
+            .chars()
+            .map(|c| format!("OUT #{}\n", c as i64))
+            .collect();
+        source += "IN -> [x]\nHLT\nx: DATA 0";
+        let mut robot = Robot::new(assemble(&source).unwrap());
+        robot.scan().unwrap();
+        assert_eq!(robot.position, Coordinate::new(2, 2));
+        assert_eq!(robot.facing, Direction::Down);
+        assert_eq!(robot.map[&Coordinate::new(2, 0)], '#' as i64);
+        assert_eq!(
+            robot.render(|v| v.map(|c| c as u8 as char).unwrap_or(' ')),
+            "..#..\n..#..\n##v##\n"
+        );
+        // It's now waiting for instructions
+        assert!(robot.cpu.running);
+    }
+}
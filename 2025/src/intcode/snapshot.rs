@@ -0,0 +1,211 @@
+use crate::intcode::intcodecpu::IntCodeCPU;
+use crate::intcode::memory::Memory;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/* Saving a CPU to disk, so a long run can pick up from a checkpoint.  The format is plain text,
+ * one field per line, so you can read (or hand edit) a snapshot if you need to:
+ *
+ *   pc 12
+ *   rb 0
+ *   running true
+ *   input 1,2
+ *   output 3
+ *   memory 1,2,3
+ *   sparse 1000000=4
+ */
+
+fn join(values: impl Iterator<Item = String>) -> String {
+    values.collect::<Vec<_>>().join(",")
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn parse_list(text: &str) -> io::Result<Vec<i64>> {
+    text.split(',')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse().map_err(|_| invalid(format!("bad number {}", s))))
+        .collect()
+}
+
+impl IntCodeCPU {
+    /* Share our memory before cloning, so the snapshot (and anything restored from it) doesn't copy
+     * it until one side writes, and the same for the decode cache.  A profile belongs to the run
+     * being measured, so a snapshot doesn't take one with it, and restoring keeps ours */
+    pub fn snapshot(&mut self) -> IntCodeCPU {
+        self.memory.share();
+        self.decoded.share();
+        let profile = self.profile.take();
+        let snapshot = self.clone();
+        self.profile = profile;
+        snapshot
+    }
+
+    pub fn restore(&mut self, snapshot: &IntCodeCPU) {
+        let profile = self.profile.take();
+        *self = snapshot.clone();
+        self.profile = profile;
+    }
+
+    pub fn to_snapshot_string(&self) -> String {
+        let mut sparse: Vec<(usize, i64)> = self.memory.sparse().collect();
+        sparse.sort();
+        [
+            format!("pc {}", self.pc),
+            format!("rb {}", self.relative_base),
+            format!("running {}", self.running),
+            format!("input {}", join(self.input.iter().map(|v| v.to_string()))),
+            format!("output {}", join(self.output.iter().map(|v| v.to_string()))),
+            format!(
+                "memory {}",
+                join(self.memory.dense().iter().map(|v| v.to_string()))
+            ),
+            format!(
+                "sparse {}",
+                join(sparse.iter().map(|(a, v)| format!("{}={}", a, v)))
+            ),
+        ]
+        .join("\n")
+            + "\n"
+    }
+
+    pub fn from_snapshot_string(text: &str) -> io::Result<IntCodeCPU> {
+        let mut cpu = IntCodeCPU::new(vec![]);
+        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+            let (field, value) = line.split_once(' ').unwrap_or((line, ""));
+            let value = value.trim();
+            match field {
+                "pc" => cpu.pc = value.parse().map_err(|_| invalid(line.to_string()))?,
+                "rb" => cpu.relative_base = value.parse().map_err(|_| invalid(line.to_string()))?,
+                "running" => cpu.running = value.parse().map_err(|_| invalid(line.to_string()))?,
+                "input" => cpu.input = parse_list(value)?.into(),
+                "output" => cpu.output = parse_list(value)?,
+                "memory" => {
+                    // Keep anything sparse we've already read in
+                    let sparse: Vec<(usize, i64)> = cpu.memory.sparse().collect();
+                    cpu.memory = Memory::new(parse_list(value)?);
+                    for (address, v) in sparse {
+                        cpu.memory.set(address, v);
+                    }
+                }
+                "sparse" => {
+                    for cell in value.split(',').filter(|s| !s.is_empty()) {
+                        let (address, v) = cell
+                            .split_once('=')
+                            .ok_or_else(|| invalid(format!("bad cell {}", cell)))?;
+                        let address = address
+                            .parse()
+                            .map_err(|_| invalid(format!("bad address {}", address)))?;
+                        let v = v
+                            .parse()
+                            .map_err(|_| invalid(format!("bad number {}", v)))?;
+                        cpu.memory.set(address, v);
+                    }
+                }
+                _ => return Err(invalid(format!("unknown field {}", field))),
+            }
+        }
+        Ok(cpu)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_snapshot_string())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<IntCodeCPU> {
+        IntCodeCPU::from_snapshot_string(&fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::intcodecpu::{IntCodeCPU, RunStatus};
+
+    // Adds up every input it's given and outputs the running total
+    fn adder() -> IntCodeCPU {
+        IntCodeCPU::new(vec![3, 11, 1, 11, 12, 12, 4, 12, 1105, 1, 0, 0, 0])
+    }
+
+    #[test]
+    fn test_snapshot_fork() {
+        let mut cpu = adder();
+        cpu.push_input(5);
+        assert_eq!(cpu.resume().unwrap(), RunStatus::Output(5));
+        let snapshot = cpu.snapshot();
+        cpu.push_input(10);
+        assert_eq!(cpu.resume().unwrap(), RunStatus::Output(15));
+        // Back out and try something different
+        cpu.restore(&snapshot);
+        cpu.push_input(1);
+        assert_eq!(cpu.resume().unwrap(), RunStatus::Output(6));
+        // The snapshot itself wasn't touched
+        assert_eq!(snapshot.memory[12], 5);
+    }
+
+    #[test]
+    fn test_snapshot_shares() {
+        let mut cpu = adder();
+        cpu.enable_profiling();
+        cpu.push_input(5);
+        cpu.resume().unwrap();
+        let snapshot = cpu.snapshot();
+        // Neither memory nor the decode cache was copied, and the profile stayed behind
+        assert!(snapshot.memory.is_shared());
+        assert!(snapshot.decoded.is_shared());
+        assert!(snapshot.profile().is_none());
+        let mut forked = adder();
+        forked.restore(&snapshot);
+        assert!(forked.memory.is_shared());
+        assert!(forked.decoded.is_shared());
+        // Restoring doesn't lose the profile we were building up either
+        let cycles = cpu.profile().unwrap().cycles;
+        cpu.restore(&snapshot);
+        assert_eq!(cpu.profile().unwrap().cycles, cycles);
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut cpu = adder();
+        cpu.push_input(5);
+        cpu.push_input(-3);
+        cpu.push_input(7);
+        assert_eq!(cpu.resume().unwrap(), RunStatus::Output(5));
+        // Only run keeps outputs, but when there are some they're saved too
+        cpu.output.push(5);
+        cpu.relative_base = 12;
+        cpu.memory.set(1_000_000, 42);
+        let text = cpu.to_snapshot_string();
+        assert_eq!(
+            text,
+            "pc 8\nrb 12\nrunning true\ninput -3,7\noutput 5\nmemory 3,11,1,11,12,12,4,12,1105,1,0,5,5\nsparse 1000000=42\n"
+        );
+        let mut restored = IntCodeCPU::from_snapshot_string(&text).unwrap();
+        assert_eq!(restored.to_snapshot_string(), text);
+        assert_eq!(restored.resume().unwrap(), RunStatus::Output(2));
+        assert_eq!(restored.resume().unwrap(), RunStatus::Output(9));
+    }
+
+    #[test]
+    fn test_snapshot_file() {
+        let path =
+            std::env::temp_dir().join(format!("intcode-snapshot-{}.txt", std::process::id()));
+        let mut cpu = adder();
+        cpu.push_input(4);
+        cpu.resume().unwrap();
+        cpu.save(&path).unwrap();
+        let mut loaded = IntCodeCPU::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        loaded.push_input(4);
+        assert_eq!(loaded.resume().unwrap(), RunStatus::Output(8));
+    }
+
+    #[test]
+    fn test_snapshot_bad_data() {
+        assert!(IntCodeCPU::from_snapshot_string("pc twelve").is_err());
+        assert!(IntCodeCPU::from_snapshot_string("memory 1,x").is_err());
+        assert!(IntCodeCPU::from_snapshot_string("colour blue").is_err());
+    }
+}
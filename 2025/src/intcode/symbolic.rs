@@ -0,0 +1,532 @@
+use crate::intcode::decode::{Instruction, Machine};
+use crate::intcode::intcodecpu::{IntCodeCPU, IntCodeErrorKind, RunStatus};
+use crate::intcode::memory::MAX_DENSE_GROWTH;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/* A polynomial in some number of variables, which is what a cell ends up holding once we've added
+ * and multiplied things that depend on the noun and verb.  Each term is keyed by the power of each
+ * variable, so noun^2*verb is [2, 1], with trailing zeros trimmed so a constant is just [].
+ * The arithmetic gives None if a coefficient won't fit in an i64 */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Polynomial {
+    terms: BTreeMap<Vec<u32>, i64>,
+}
+
+fn trim(mut powers: Vec<u32>) -> Vec<u32> {
+    while powers.last() == Some(&0) {
+        powers.pop();
+    }
+    powers
+}
+
+impl Polynomial {
+    pub fn constant(value: i64) -> Self {
+        let mut polynomial = Polynomial::default();
+        if value != 0 {
+            polynomial.terms.insert(vec![], value);
+        }
+        polynomial
+    }
+
+    pub fn variable(index: usize) -> Self {
+        let mut powers = vec![0; index + 1];
+        powers[index] = 1;
+        let mut polynomial = Polynomial::default();
+        polynomial.terms.insert(powers, 1);
+        polynomial
+    }
+
+    fn add_term(&mut self, powers: Vec<u32>, coefficient: i64) -> Option<()> {
+        let entry = self.terms.entry(trim(powers)).or_insert(0);
+        *entry = entry.checked_add(coefficient)?;
+        if *entry == 0 {
+            self.terms.retain(|_, c| *c != 0);
+        }
+        Some(())
+    }
+
+    // Just a number, with nothing symbolic left in it
+    pub fn as_constant(&self) -> Option<i64> {
+        match self.terms.iter().next() {
+            None => Some(0),
+            Some((powers, coefficient)) if self.terms.len() == 1 && powers.is_empty() => {
+                Some(*coefficient)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn add(&self, other: &Polynomial) -> Option<Polynomial> {
+        let mut result = self.clone();
+        for (powers, coefficient) in &other.terms {
+            result.add_term(powers.clone(), *coefficient)?;
+        }
+        Some(result)
+    }
+
+    pub fn mul(&self, other: &Polynomial) -> Option<Polynomial> {
+        let mut result = Polynomial::default();
+        for (a, x) in &self.terms {
+            for (b, y) in &other.terms {
+                let powers = (0..a.len().max(b.len()))
+                    .map(|i| a.get(i).unwrap_or(&0) + b.get(i).unwrap_or(&0))
+                    .collect();
+                result.add_term(powers, x.checked_mul(*y)?)?;
+            }
+        }
+        Some(result)
+    }
+
+    // Replace a variable with a value, leaving the rest symbolic
+    pub fn substitute(&self, index: usize, value: i64) -> Option<Polynomial> {
+        let mut result = Polynomial::default();
+        for (powers, coefficient) in &self.terms {
+            let mut powers = powers.clone();
+            let power = powers.get(index).copied().unwrap_or(0);
+            if power > 0 {
+                powers[index] = 0;
+            }
+            result.add_term(powers, coefficient.checked_mul(value.checked_pow(power)?)?)?;
+        }
+        Some(result)
+    }
+
+    // The highest power of a variable anywhere in the polynomial
+    pub fn degree(&self, index: usize) -> u32 {
+        self.terms
+            .keys()
+            .map(|powers| powers.get(index).copied().unwrap_or(0))
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn display<'a>(&'a self, names: &'a [&str]) -> impl fmt::Display + 'a {
+        PolynomialDisplay {
+            polynomial: self,
+            names,
+        }
+    }
+}
+
+struct PolynomialDisplay<'a> {
+    polynomial: &'a Polynomial,
+    names: &'a [&'a str],
+}
+
+impl fmt::Display for PolynomialDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.polynomial.terms.is_empty() {
+            return write!(f, "0");
+        }
+        // Biggest powers first, which puts the constant last
+        for (index, (powers, coefficient)) in self.polynomial.terms.iter().rev().enumerate() {
+            let mut factors: Vec<String> = powers
+                .iter()
+                .enumerate()
+                .filter(|(_, power)| **power > 0)
+                .map(|(variable, power)| {
+                    let name = self.names.get(variable).copied().unwrap_or("?");
+                    if *power == 1 {
+                        name.to_string()
+                    } else {
+                        format!("{}^{}", name, power)
+                    }
+                })
+                .collect();
+            if factors.is_empty() || coefficient.abs() != 1 {
+                factors.insert(0, coefficient.abs().to_string());
+            }
+            let sign = match (index, *coefficient < 0) {
+                (0, true) => "-",
+                (0, false) => "",
+                (_, true) => " - ",
+                (_, false) => " + ",
+            };
+            write!(f, "{}{}", sign, factors.join("*"))?;
+        }
+        Ok(())
+    }
+}
+
+/* Why we had to give up.  The symbolic CPU only understands programs whose code, branches and write
+ * addresses don't depend on the variables, anything else needs running for real */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolicError {
+    SymbolicOpcode(usize),
+    SymbolicAddress(usize),
+    SymbolicBranch(usize),
+    // We've no way of giving a symbolic program input
+    Input(usize),
+    // A write so far past the end of memory that it's not a program we're going to solve
+    OutOfRange(usize),
+    // The arithmetic got too big for an i64
+    Overflow(usize),
+    Crashed(usize, IntCodeErrorKind),
+}
+
+impl fmt::Display for SymbolicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymbolicError::SymbolicOpcode(pc) => {
+                write!(f, "opcode depends on a variable at pc {}", pc)
+            }
+            SymbolicError::SymbolicAddress(pc) => {
+                write!(f, "write address depends on a variable at pc {}", pc)
+            }
+            SymbolicError::SymbolicBranch(pc) => {
+                write!(f, "branch depends on a variable at pc {}", pc)
+            }
+            SymbolicError::Input(pc) => write!(f, "input needed at pc {}", pc),
+            SymbolicError::OutOfRange(pc) => write!(f, "write out of range at pc {}", pc),
+            SymbolicError::Overflow(pc) => write!(f, "overflow at pc {}", pc),
+            SymbolicError::Crashed(pc, kind) => write!(f, "{} at pc {}", kind, pc),
+        }
+    }
+}
+
+impl std::error::Error for SymbolicError {}
+
+/* Runs a program with some of its cells replaced by variables, so every cell ends up as a polynomial
+ * in those variables.  It runs the same instructions as IntCodeCPU, just with memory holding Polynomials.
+ * Reading from an address that depends on a variable gives a value we can't know, which is None.
+ * That's fine as long as nothing important uses it, day 2 starts by reading [noun] and [verb] into a
+ * cell that's overwritten straight away */
+pub struct SymbolicCPU {
+    pub memory: Vec<Option<Polynomial>>,
+    pub pc: usize,
+    pub relative_base: i64,
+    pub output: Vec<Option<Polynomial>>,
+}
+
+impl SymbolicCPU {
+    pub fn new(program: &[i64], variables: &[usize]) -> Self {
+        let mut memory: Vec<Option<Polynomial>> = program
+            .iter()
+            .map(|v| Some(Polynomial::constant(*v)))
+            .collect();
+        for (index, &address) in variables.iter().enumerate() {
+            if address >= memory.len() {
+                memory.resize(address + 1, Some(Polynomial::default()));
+            }
+            memory[address] = Some(Polynomial::variable(index));
+        }
+        SymbolicCPU {
+            memory,
+            pc: 0,
+            relative_base: 0,
+            output: vec![],
+        }
+    }
+
+    pub fn get(&self, address: usize) -> Option<Polynomial> {
+        match self.memory.get(address) {
+            Some(value) => value.clone(),
+            None => Some(Polynomial::default()),
+        }
+    }
+
+    fn set(&mut self, address: usize, value: Option<Polynomial>) -> Result<(), SymbolicError> {
+        if address >= self.memory.len() {
+            // The same limit real memory grows by, a program writing further out isn't one we solve
+            if address >= self.memory.len() + MAX_DENSE_GROWTH {
+                return Err(SymbolicError::OutOfRange(self.pc));
+            }
+            self.memory.resize(address + 1, Some(Polynomial::default()));
+        }
+        self.memory[address] = value;
+        Ok(())
+    }
+
+    fn known(&self, address: usize) -> Option<i64> {
+        self.get(address)?.as_constant()
+    }
+
+    pub fn run(&mut self) -> Result<(), SymbolicError> {
+        loop {
+            let pc = self.pc;
+            let opcode = self.known(pc).ok_or(SymbolicError::SymbolicOpcode(pc))?;
+            let instruction =
+                Instruction::decode(opcode).map_err(|kind| SymbolicError::Crashed(pc, kind))?;
+            match instruction.execute(self)? {
+                RunStatus::Output(value) => self.output.push(value),
+                RunStatus::Halted => return Ok(()),
+                RunStatus::Running | RunStatus::NeedsInput => {}
+            }
+        }
+    }
+}
+
+impl Machine for SymbolicCPU {
+    // None for a value we can't know
+    type Value = Option<Polynomial>;
+    type Error = SymbolicError;
+
+    fn pc(&self) -> usize {
+        self.pc
+    }
+
+    fn set_pc(&mut self, pc: usize) {
+        self.pc = pc;
+    }
+
+    fn relative_base(&self) -> i64 {
+        self.relative_base
+    }
+
+    fn set_relative_base(&mut self, relative_base: i64) {
+        self.relative_base = relative_base;
+    }
+
+    fn crash(&self, kind: IntCodeErrorKind) -> SymbolicError {
+        SymbolicError::Crashed(self.pc, kind)
+    }
+
+    fn word(&self, address: usize) -> Option<i64> {
+        self.known(address)
+    }
+
+    fn immediate(&self, address: usize) -> Option<Polynomial> {
+        self.get(address)
+    }
+
+    fn load(&mut self, address: usize) -> Option<Polynomial> {
+        self.get(address)
+    }
+
+    fn store(&mut self, address: usize, value: Option<Polynomial>) -> Result<(), SymbolicError> {
+        self.set(address, value)
+    }
+
+    // Reading from somewhere that depends on a variable is fine, as long as nothing uses what we read
+    fn unknown_read(&self) -> Result<Option<Polynomial>, SymbolicError> {
+        Ok(None)
+    }
+
+    fn unknown_write(&self) -> SymbolicError {
+        SymbolicError::SymbolicAddress(self.pc)
+    }
+
+    fn constant(value: i64) -> Option<Polynomial> {
+        Some(Polynomial::constant(value))
+    }
+
+    fn concrete(&self, value: Option<Polynomial>) -> Result<i64, SymbolicError> {
+        value
+            .and_then(|value| value.as_constant())
+            .ok_or(SymbolicError::SymbolicBranch(self.pc))
+    }
+
+    fn add(
+        &self,
+        a: Option<Polynomial>,
+        b: Option<Polynomial>,
+    ) -> Result<Option<Polynomial>, SymbolicError> {
+        match a.zip(b) {
+            Some((a, b)) => a.add(&b).map(Some).ok_or(SymbolicError::Overflow(self.pc)),
+            None => Ok(None),
+        }
+    }
+
+    fn mul(
+        &self,
+        a: Option<Polynomial>,
+        b: Option<Polynomial>,
+    ) -> Result<Option<Polynomial>, SymbolicError> {
+        match a.zip(b) {
+            Some((a, b)) => a.mul(&b).map(Some).ok_or(SymbolicError::Overflow(self.pc)),
+            None => Ok(None),
+        }
+    }
+
+    // We've no way of giving a symbolic program input
+    fn input(&mut self) -> Result<Option<Option<Polynomial>>, SymbolicError> {
+        Err(SymbolicError::Input(self.pc))
+    }
+
+    fn halt(&mut self) {}
+}
+
+/* Find a value from 0 to 99 for the only variable left in a polynomial, so it comes out as target.
+ * The outer None is for when the arithmetic overflows and we can't say either way */
+fn solve_one(polynomial: &Polynomial, variable: usize, target: i64) -> Option<Option<i64>> {
+    if polynomial.degree(variable) <= 1 {
+        // It's a straight line, a * x + b = target
+        let b = polynomial.substitute(variable, 0)?.as_constant()?;
+        let a = polynomial
+            .substitute(variable, 1)?
+            .as_constant()?
+            .checked_sub(b)?;
+        let difference = target.checked_sub(b)?;
+        if a == 0 {
+            return Some((difference == 0).then_some(0));
+        }
+        let x = difference.checked_div(a)?;
+        return Some((difference.checked_rem(a)? == 0 && (0..100).contains(&x)).then_some(x));
+    }
+    for x in 0..100 {
+        if polynomial.substitute(variable, x)?.as_constant()? == target {
+            return Some(Some(x));
+        }
+    }
+    Some(None)
+}
+
+/* Day 2 part 2.  Work out cell 0 as a polynomial in the noun (cell 1) and verb (cell 2), then for each
+ * noun solve for the verb.  If the program won't run symbolically, cell 0 ends up as something we can't
+ * know, or the arithmetic overflows, we just try them all for real */
+pub fn solve_noun_verb(program: &[i64], target: i64) -> Option<(i64, i64)> {
+    let mut cpu = SymbolicCPU::new(program, &[1, 2]);
+    let Some(result) = cpu.run().ok().and_then(|_| cpu.get(0)) else {
+        return brute_force_noun_verb(program, target);
+    };
+    for noun in 0..100 {
+        match result
+            .substitute(0, noun)
+            .and_then(|p| solve_one(&p, 1, target))
+        {
+            Some(Some(verb)) => return Some((noun, verb)),
+            Some(None) => {}
+            None => return brute_force_noun_verb(program, target),
+        }
+    }
+    None
+}
+
+pub fn brute_force_noun_verb(program: &[i64], target: i64) -> Option<(i64, i64)> {
+    for noun in 0..100 {
+        for verb in 0..100 {
+            let mut memory = program.to_vec();
+            memory[1] = noun;
+            memory[2] = verb;
+            let mut cpu = IntCodeCPU::new(memory);
+            // Some noun/verb pairs make the program crash, those clearly aren't the answer
+            if cpu.run().is_ok() && cpu.memory[0] == target {
+                return Some((noun, verb));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::intcode::assembler::assemble;
+    use crate::intcode::parse_program;
+    use crate::intcode::symbolic::*;
+
+    #[test]
+    fn test_polynomial() {
+        let noun = Polynomial::variable(0);
+        let verb = Polynomial::variable(1);
+        let p = noun
+            .mul(&Polynomial::constant(3))
+            .and_then(|p| p.add(&verb))
+            .and_then(|p| p.add(&Polynomial::constant(-4)))
+            .unwrap();
+        assert_eq!(
+            p.display(&["noun", "verb"]).to_string(),
+            "3*noun + verb - 4"
+        );
+        let square = p.mul(&noun).unwrap();
+        assert_eq!(
+            square.display(&["noun", "verb"]).to_string(),
+            "3*noun^2 + noun*verb - 4*noun"
+        );
+        assert_eq!(
+            square
+                .substitute(0, 2)
+                .and_then(|p| p.substitute(1, 5))
+                .and_then(|p| p.as_constant()),
+            Some(14)
+        );
+        assert_eq!(
+            p.add(&p.mul(&Polynomial::constant(-1)).unwrap()),
+            Some(Polynomial::default())
+        );
+        assert_eq!(Polynomial::default().as_constant(), Some(0));
+        // Anything too big for an i64 is no use to us
+        let big = Polynomial::constant(i64::MAX);
+        assert_eq!(big.add(&Polynomial::constant(1)), None);
+        assert_eq!(big.mul(&noun).unwrap().mul(&Polynomial::constant(2)), None);
+        assert_eq!(noun.substitute(0, 2).unwrap().as_constant(), Some(2));
+        assert_eq!(square.substitute(0, i64::MAX), None);
+    }
+
+    #[test]
+    fn test_symbolic_day2_example() {
+        let mut cpu = SymbolicCPU::new(&parse_program("1,9,10,3,2,3,11,0,99,30,40,50"), &[9, 10]);
+        cpu.run().unwrap();
+        assert_eq!(
+            cpu.get(0).unwrap().display(&["a", "b"]).to_string(),
+            "50*a + 50*b"
+        );
+    }
+
+    #[test]
+    fn test_solve_noun_verb() {
+        // cell 0 = noun * verb + 7
+        let program = assemble(
+            "   MUL #0, #0 -> [0]
+                ADD [0], #7 -> [0]
+                HLT",
+        )
+        .unwrap();
+        assert_eq!(solve_noun_verb(&program, 2007), Some((25, 80)));
+        assert_eq!(solve_noun_verb(&program, 100_000), None);
+    }
+
+    #[test]
+    fn test_solve_noun_verb_falls_back() {
+        // Branching on the noun means we can't do it symbolically, but we should still get the answer
+        let program = assemble(
+            "   ADD #0, #0 -> [0]
+                LT [0], #100 -> [t]
+                JT [t], #done
+                MUL [0], #2 -> [0]
+        done:   HLT
+        t:      DATA 0",
+        )
+        .unwrap();
+        let mut cpu = SymbolicCPU::new(&program, &[1, 2]);
+        assert_eq!(cpu.run(), Err(SymbolicError::SymbolicBranch(4)));
+        assert_eq!(solve_noun_verb(&program, 60), Some((0, 60)));
+        assert_eq!(solve_noun_verb(&program, 300), Some((51, 99)));
+    }
+
+    #[test]
+    fn test_solve_noun_verb_overflow() {
+        // (noun + verb) * 2^64 doesn't fit, even though it comes out as 0 for the answer
+        let program = assemble(
+            "   ADD #0, #0 -> [t]
+                MUL [t], #4611686018427387904 -> [t]
+                MUL [t], #4 -> [t]
+                ADD [t], #7 -> [0]
+                HLT
+        t:      DATA 0",
+        )
+        .unwrap();
+        let mut cpu = SymbolicCPU::new(&program, &[1, 2]);
+        assert_eq!(cpu.run(), Err(SymbolicError::Overflow(8)));
+        assert_eq!(solve_noun_verb(&program, 7), Some((0, 0)));
+    }
+
+    #[test]
+    fn test_symbolic_variables_past_the_end() {
+        let mut cpu = SymbolicCPU::new(&parse_program("1,10,10,0,99"), &[10]);
+        cpu.run().unwrap();
+        assert_eq!(cpu.get(0).unwrap().display(&["x"]).to_string(), "2*x");
+    }
+
+    #[test]
+    fn test_solve_noun_verb_real_input() {
+        let program = parse_program(&std::fs::read_to_string("input/intcode/day02.txt").unwrap());
+        let mut cpu = SymbolicCPU::new(&program, &[1, 2]);
+        cpu.run().unwrap();
+        assert_eq!(cpu.get(0).unwrap().degree(1), 1);
+        assert_eq!(
+            solve_noun_verb(&program, 19690720),
+            brute_force_noun_verb(&program, 19690720)
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use std::cell::{Cell, RefCell};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Once, mpsc};
+use std::thread;
+use std::time::Duration;
+
+/* Running a solver on its own thread, so that a panic (plenty of them unwrap or have a todo!() in
+ * them) is reported rather than taking the whole run down with it, and one that's stuck in a loop
+ * can be given up on.  We've no way to stop a thread, so a part that times out is left spinning in
+ * the background until the runner exits */
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome<T> {
+    Finished(T),
+    // The panic message and where it happened
+    Panicked(String),
+    TimedOut,
+}
+
+thread_local! {
+    static ISOLATED: Cell<bool> = const { Cell::new(false) };
+    static LAST_PANIC: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+static INSTALL_HOOK: Once = Once::new();
+
+/* The default hook prints the panic straight to stderr, which in the middle of the runner's output
+ * is just noise since we report it ourselves.  Panics anywhere else still go to the default hook */
+fn install_hook() {
+    INSTALL_HOOK.call_once(|| {
+        let default = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !ISOLATED.with(|isolated| isolated.get()) {
+                return default(info);
+            }
+            let message = info.payload_as_str().unwrap_or("unknown panic");
+            let message = match info.location() {
+                Some(location) => format!("{} at {}", message, location),
+                None => message.to_string(),
+            };
+            LAST_PANIC.with(|last| *last.borrow_mut() = Some(message));
+        }));
+    });
+}
+
+// Run f on a new thread, waiting no longer than timeout for it to finish
+pub fn run_isolated<T: Send + 'static>(
+    timeout: Option<Duration>,
+    f: impl FnOnce() -> T + Send + 'static,
+) -> Outcome<T> {
+    install_hook();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        ISOLATED.with(|isolated| isolated.set(true));
+        let outcome = match panic::catch_unwind(AssertUnwindSafe(f)) {
+            Ok(result) => Outcome::Finished(result),
+            Err(_) => Outcome::Panicked(
+                LAST_PANIC
+                    .with(|last| last.borrow_mut().take())
+                    .unwrap_or_else(|| "unknown panic".to_string()),
+            ),
+        };
+        // If nobody's listening we timed out, and there's no one left to tell
+        let _ = sender.send(outcome);
+    });
+    match timeout {
+        Some(timeout) => match receiver.recv_timeout(timeout) {
+            Ok(outcome) => outcome,
+            Err(mpsc::RecvTimeoutError::Timeout) => Outcome::TimedOut,
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                Outcome::Panicked("solver thread went away".to_string())
+            }
+        },
+        None => receiver
+            .recv()
+            .unwrap_or_else(|_| Outcome::Panicked("solver thread went away".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::isolate::*;
+
+    #[test]
+    fn test_isolated_finishes() {
+        assert_eq!(
+            run_isolated(Some(Duration::from_secs(10)), || 6 * 7),
+            Outcome::Finished(42)
+        );
+        assert_eq!(run_isolated(None, || "done"), Outcome::Finished("done"));
+    }
+
+    #[test]
+    fn test_isolated_panics() {
+        let Outcome::Panicked(message) = run_isolated(None, || -> i32 { panic!("boom") }) else {
+            panic!("Expected a panic");
+        };
+        assert!(
+            message.starts_with("boom at src/isolate.rs:"),
+            "{}",
+            message
+        );
+        // Formatted messages come through too
+        let outcome = run_isolated(None, || -> i32 { todo!("part {}", 2) });
+        assert!(
+            matches!(outcome, Outcome::Panicked(m) if m.starts_with("not yet implemented: part 2"))
+        );
+    }
+
+    #[test]
+    fn test_isolated_times_out() {
+        let outcome = run_isolated(Some(Duration::from_millis(10)), || {
+            thread::sleep(Duration::from_secs(2));
+            1
+        });
+        assert_eq!(outcome, Outcome::TimedOut);
+    }
+}
@@ -0,0 +1,3 @@
+pub mod intcode;
+pub mod solver;
+pub mod utils;
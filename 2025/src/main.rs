@@ -1,72 +1,20 @@
+use answers::{Answers, Verdict};
+use bench::{Timings, format_duration};
 use clap::Parser;
+use isolate::{Outcome, run_isolated};
+use mbsaoc2025::solver::{Registration, solvers};
+use mbsaoc2025::utils;
 use std::fs;
-mod intcode;
-use std::time::Instant;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+mod answers;
+mod bench;
 mod day1;
 mod day2;
 mod day3;
 mod day4;
-mod utils;
-
-const DAYS: &[Day] = &[
-    Day {
-        year: 2019,
-        day: 2,
-        name: "Intcode 2019 Day 2",
-        filename: "input/intcode/day02.txt",
-        solve_part1: intcode::intcode2::calculate_part1,
-        solve_part2: intcode::intcode2::calculate_part2,
-    },
-    Day {
-        year: 2019,
-        day: 5,
-        name: "Intcode 2019 Day 5",
-        filename: "input/intcode/day05.txt",
-        solve_part1: intcode::intcode5::calculate_part1,
-        solve_part2: intcode::intcode5::calculate_part2,
-    },
-    Day {
-        year: 2025,
-        day: 1,
-        name: "2025 Day 1",
-        filename: "input/2025/day01.txt",
-        solve_part1: day1::calculate_part1,
-        solve_part2: day1::calculate_part2,
-    },
-    Day {
-        year: 2025,
-        day: 2,
-        name: "2025 Day 2",
-        filename: "input/2025/day02.txt",
-        solve_part1: day2::calculate_part1,
-        solve_part2: day2::calculate_part2,
-    },
-    Day {
-        year: 2025,
-        day: 3,
-        name: "2025 Day 3",
-        filename: "input/2025/day03.txt",
-        solve_part1: day3::calculate_part1,
-        solve_part2: day3::calculate_part2,
-    },
-    Day {
-        year: 2025,
-        day: 4,
-        name: "2025 Day 4",
-        filename: "input/2025/day04.txt",
-        solve_part1: day4::calculate_part1,
-        solve_part2: day4::calculate_part2,
-    },
-    // Add more days here as needed
-];
-struct Day {
-    year: u16,
-    day: u8,
-    name: &'static str,
-    filename: &'static str,
-    solve_part1: fn(&str) -> usize,
-    solve_part2: fn(&str) -> usize,
-}
+mod isolate;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -75,18 +23,107 @@ struct Args {
     year: Option<u16>,
     #[arg(short, long)]
     day: Option<u8>,
+    /// The answers we've already had accepted, to check against
+    #[arg(short, long, default_value = "answers.toml")]
+    answers: String,
+    /// Save any answers we didn't know yet into the answers file
+    #[arg(short, long)]
+    record: bool,
+    /// Time each part over this many runs, after a few to warm up
+    #[arg(short, long)]
+    bench: Option<usize>,
+    /// Just run the examples from the puzzles rather than the real inputs
+    #[arg(short, long)]
+    examples: bool,
+    /// Give up on a part after this many seconds (for each run, when benchmarking), 0 to wait forever
+    #[arg(short, long, default_value_t = 60.0)]
+    timeout: f64,
+}
+
+// How every part (and example) we ran went
+#[derive(Debug, Default)]
+struct Summary {
+    passed: usize,
+    failed: usize,
+    timed_out: usize,
+}
+
+impl Summary {
+    fn count<T>(&mut self, outcome: &Outcome<T>, correct: bool) {
+        match outcome {
+            Outcome::Finished(_) if correct => self.passed += 1,
+            Outcome::Finished(_) | Outcome::Panicked(_) => self.failed += 1,
+            Outcome::TimedOut => self.timed_out += 1,
+        }
+    }
+}
+
+// What to print for a part that didn't come back with an answer
+fn describe<T>(outcome: &Outcome<T>) -> String {
+    match outcome {
+        Outcome::Finished(_) => "finished".to_string(),
+        Outcome::Panicked(message) => format!("PANICKED: {}", message),
+        Outcome::TimedOut => "TIMED OUT".to_string(),
+    }
+}
+
+// Run every example for a day, each on its own in case it panics
+fn run_examples(day: &'static Registration, timeout: Option<Duration>, summary: &mut Summary) {
+    for (index, example) in day.examples.iter().enumerate() {
+        let outcome = run_isolated(timeout, move || day.run_example(example));
+        let result = match &outcome {
+            Outcome::Finished(answer) if answer == example.expected => {
+                format!("{} ({})", answer, Verdict::Correct)
+            }
+            Outcome::Finished(answer) => {
+                let verdict = Verdict::Wrong {
+                    expected: example.expected.to_string(),
+                };
+                format!("{} ({})", answer, verdict)
+            }
+            _ => describe(&outcome),
+        };
+        summary.count(
+            &outcome,
+            matches!(&outcome, Outcome::Finished(answer) if answer == example.expected),
+        );
+        println!("  Example {} part {}: {}", index + 1, example.part, result);
+    }
+    if day.examples.is_empty() {
+        println!("  No examples");
+    }
 }
-fn main() -> std::io::Result<()> {
+
+fn main() -> ExitCode {
     let args: Args = Args::parse();
-    if args.year.is_none() && args.day.is_none() {
-        for day in DAYS {
+    if args.year.is_none() && args.day.is_none() && !args.examples {
+        for day in solvers() {
             println!("{} — {} is {}", day.year, day.day, day.name);
         }
-        std::process::exit(1);
+        return ExitCode::FAILURE;
     }
+    let mut answers = match Answers::load(&args.answers) {
+        Ok(answers) => answers,
+        Err(e) => {
+            println!("Error reading {}: {}", args.answers, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let timeout = (args.timeout > 0.0).then(|| Duration::from_secs_f64(args.timeout));
+    // A tenth of the runs again to warm up seems to be plenty
+    let (warmup, runs) = match args.bench {
+        Some(runs) => (runs.div_ceil(10), runs),
+        None => (0, 1),
+    };
+    // The timeout is for a single run, and benchmarking does them all in one go
+    let bench_timeout = timeout.map(|t| t * (warmup + runs) as u32);
+    let mut summary = Summary::default();
+    let mut regressions = 0;
+    let mut recorded = 0;
+    let loaded = answers.clone();
     let total_start = Instant::now();
 
-    for day in DAYS {
+    for day in solvers() {
         match args.year {
             Some(y) if y != day.year => continue,
             _ => {}
@@ -95,25 +132,122 @@ fn main() -> std::io::Result<()> {
             Some(d) if d != day.day => continue,
             _ => {}
         }
+        if args.examples {
+            println!("Day {} — examples", day.name);
+            run_examples(day, timeout, &mut summary);
+            continue;
+        }
         println!("Day {} — {}", day.name, day.filename);
-        match fs::read_to_string(&day.filename) {
-            Ok(content) => {
-                let before = Instant::now();
-                let part1 = (day.solve_part1)(&content);
-                let p1_dur = before.elapsed();
-
-                let before = Instant::now();
-                let part2 = (day.solve_part2)(&content);
-                let p2_dur = before.elapsed();
-
-                println!("  Part 1: {} in {} ms", part1, p1_dur.as_millis());
-                println!("  Part 2: {} in {} ms", part2, p2_dur.as_millis());
-            }
+        let content = match fs::read_to_string(day.filename) {
+            Ok(content) => Arc::new(content),
             Err(e) => {
                 println!("  Error reading file: {}", e);
+                continue;
+            }
+        };
+        // Answers for some other input are no help, and we'll want to know the examples work
+        let changed = answers.input_changed(day.year, day.day, &content);
+        if changed {
+            println!("  Input has changed since the answers were recorded");
+            answers.forget(day.year, day.day);
+        }
+        let input = Arc::clone(&content);
+        let parsed = match run_isolated(bench_timeout, move || {
+            Timings::measure(warmup, runs, || (day.parse)(&input))
+        }) {
+            Outcome::Finished((parsed, timings)) => {
+                if args.bench.is_some() {
+                    println!("  Parse:  {}", timings);
+                } else {
+                    println!("  Parsed in {}", format_duration(timings.min()));
+                }
+                Some(Arc::new(parsed))
+            }
+            // Neither part can run without the input, so they both count against the day
+            outcome => {
+                println!("  Parse: {}", describe(&outcome));
+                summary.count(&outcome, false);
+                summary.count(&outcome, false);
+                None
+            }
+        };
+        let mut all_passed = parsed.is_some();
+        let mut wrong = false;
+        for (part, solve) in [(1, day.part1), (2, day.part2)] {
+            let Some(parsed) = &parsed else { break };
+            let parsed = Arc::clone(parsed);
+            let outcome = run_isolated(bench_timeout, move || {
+                Timings::measure(warmup, runs, || solve(&parsed))
+            });
+            let Outcome::Finished((answer, timings)) = &outcome else {
+                println!("  Part {}: {}", part, describe(&outcome));
+                summary.count(&outcome, false);
+                all_passed = false;
+                continue;
+            };
+            let verdict = answers.check(day.year, day.day, part, answer);
+            let correct = !matches!(verdict, Verdict::Wrong { .. });
+            if args.bench.is_some() {
+                println!("  Part {}: {} ({})", part, answer, verdict);
+                println!("          {}", timings);
+            } else {
+                println!(
+                    "  Part {}: {} in {} ({})",
+                    part,
+                    answer,
+                    format_duration(timings.min()),
+                    verdict
+                );
+            }
+            match verdict {
+                Verdict::Wrong { .. } => {
+                    regressions += 1;
+                    wrong = true;
+                    all_passed = false;
+                }
+                Verdict::Unknown if args.record => {
+                    answers.record(day.year, day.day, part, answer);
+                    recorded += 1;
+                }
+                _ => {}
+            }
+            summary.count(&outcome, correct);
+        }
+        // Remember which input the answers belong to, as long as they all agree with it
+        if args.record && parsed.is_some() && !wrong {
+            answers.record_input(day.year, day.day, &content);
+        }
+        // Whether the examples still work tells us if it's the solver or the input
+        if changed || !all_passed {
+            run_examples(day, timeout, &mut summary);
+        }
+    }
+    println!("Total time: {}", format_duration(total_start.elapsed()));
+    if args.record && answers != loaded {
+        match answers.save(&args.answers) {
+            Ok(()) => println!("Updated {} with {} new answers", args.answers, recorded),
+            Err(e) => {
+                println!("Error saving {}: {}", args.answers, e);
+                return ExitCode::FAILURE;
             }
         }
     }
-    println!("Total time: {} ms", total_start.elapsed().as_millis());
-    Ok(())
+    // Recording never overwrites, a wrong answer has to be fixed (or removed from the file) by hand
+    if regressions > 0 {
+        println!("{} answers don't match the ones we recorded", regressions);
+    }
+    println!(
+        "Passed: {}, failed: {}, timed out: {}",
+        summary.passed, summary.failed, summary.timed_out
+    );
+    // We can't stop a thread, so anything that timed out skews the timings of everything after it
+    if summary.timed_out > 0 {
+        println!(
+            "Parts that timed out are still running, so anything after them ran on a busier machine"
+        );
+    }
+    if summary.failed > 0 || summary.timed_out > 0 {
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
 }
@@ -0,0 +1,210 @@
+use std::any::Any;
+use std::fmt::Display;
+
+#[doc(hidden)]
+pub use inventory;
+
+/* Every day implements Solver.  The input is parsed once into whatever suits the day, both parts
+ * work from that, and the answers can be anything we can print, so a day that draws letters or
+ * needs an i64 doesn't have to squeeze its answer into a usize */
+pub trait Solver {
+    const YEAR: u16;
+    const DAY: u8;
+    const NAME: &'static str;
+    // Relative to the 2025 directory, where the runner is run from
+    const INPUT: &'static str;
+
+    type Input: Send + Sync + 'static;
+    type Part1: Display;
+    type Part2: Display;
+
+    // The examples from the puzzle, which the runner can check with --examples and the tests can share
+    const EXAMPLES: &'static [Example] = &[];
+
+    fn parse(input: &str) -> Self::Input;
+    fn part1(input: &Self::Input) -> Self::Part1;
+    fn part2(input: &Self::Input) -> Self::Part2;
+
+    // Straight from the text, which is what most tests want
+    fn solve_part1(input: &str) -> Self::Part1 {
+        Self::part1(&Self::parse(input))
+    }
+
+    fn solve_part2(input: &str) -> Self::Part2 {
+        Self::part2(&Self::parse(input))
+    }
+}
+
+/* An example input and the answer the puzzle says it gives.  The answer is written the way it's
+ * displayed, so it works whatever type the part returns */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Example {
+    pub part: u8,
+    pub input: &'static str,
+    pub expected: &'static str,
+}
+
+impl Example {
+    pub const fn part1(input: &'static str, expected: &'static str) -> Self {
+        Example {
+            part: 1,
+            input,
+            expected,
+        }
+    }
+
+    pub const fn part2(input: &'static str, expected: &'static str) -> Self {
+        Example {
+            part: 2,
+            input,
+            expected,
+        }
+    }
+}
+
+// Parsed input, with the type hidden so the runner can treat every day the same
+pub type Parsed = Box<dyn Any + Send + Sync>;
+
+/* What the runner knows about a registered solver.  The functions wrap up the Solver's own, turning
+ * the parsed input into a Parsed and the answers into Strings */
+pub struct Registration {
+    pub year: u16,
+    pub day: u8,
+    pub name: &'static str,
+    pub filename: &'static str,
+    pub parse: fn(&str) -> Parsed,
+    pub part1: fn(&Parsed) -> String,
+    pub part2: fn(&Parsed) -> String,
+    pub examples: &'static [Example],
+}
+
+fn input<S: Solver>(parsed: &Parsed) -> &S::Input {
+    parsed
+        .downcast_ref()
+        .expect("Parsed input belongs to a different solver")
+}
+
+impl Registration {
+    pub const fn of<S: Solver>() -> Self {
+        Registration {
+            year: S::YEAR,
+            day: S::DAY,
+            name: S::NAME,
+            filename: S::INPUT,
+            parse: |text| Box::new(S::parse(text)),
+            part1: |parsed| S::part1(input::<S>(parsed)).to_string(),
+            part2: |parsed| S::part2(input::<S>(parsed)).to_string(),
+            examples: S::EXAMPLES,
+        }
+    }
+
+    pub fn solve(&self, part: u8, parsed: &Parsed) -> String {
+        match part {
+            1 => (self.part1)(parsed),
+            2 => (self.part2)(parsed),
+            _ => panic!("There's no part {}", part),
+        }
+    }
+
+    pub fn run_example(&self, example: &Example) -> String {
+        self.solve(example.part, &(self.parse)(example.input))
+    }
+}
+
+// For tests, so every day can check its examples with one line
+pub fn check_examples<S: Solver>() {
+    let registration = Registration::of::<S>();
+    for (index, example) in S::EXAMPLES.iter().enumerate() {
+        assert_eq!(
+            registration.run_example(example),
+            example.expected,
+            "{} example {} part {}",
+            S::NAME,
+            index + 1,
+            example.part
+        );
+    }
+}
+
+inventory::collect!(Registration);
+
+/* Register a solver with the runner, from anywhere in the binary or the library.  Put this next to
+ * the impl and the day turns up without having to add it to a list somewhere else */
+#[macro_export]
+macro_rules! register_solver {
+    ($solver:ty) => {
+        $crate::solver::inventory::submit! {
+            $crate::solver::Registration::of::<$solver>()
+        }
+    };
+}
+
+// Everything that's registered, in order of year and day
+pub fn solvers() -> Vec<&'static Registration> {
+    let mut solvers: Vec<_> = inventory::iter::<Registration>.into_iter().collect();
+    solvers.sort_by_key(|s| (s.year, s.day));
+    solvers
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::solver::*;
+
+    struct Sums;
+
+    impl Solver for Sums {
+        const YEAR: u16 = 1;
+        const DAY: u8 = 1;
+        const NAME: &'static str = "Sums";
+        const INPUT: &'static str = "input/none.txt";
+
+        type Input = Vec<i64>;
+        type Part1 = i64;
+        type Part2 = String;
+
+        const EXAMPLES: &'static [Example] =
+            &[Example::part1("1,2,3", "6"), Example::part2("-1,1", "-1+1")];
+
+        fn parse(input: &str) -> Self::Input {
+            input.split(',').map(|n| n.parse().unwrap()).collect()
+        }
+
+        fn part1(input: &Self::Input) -> i64 {
+            input.iter().sum()
+        }
+
+        fn part2(input: &Self::Input) -> String {
+            input
+                .iter()
+                .map(|n| n.to_string())
+                .collect::<Vec<_>>()
+                .join("+")
+        }
+    }
+
+    crate::register_solver!(Sums);
+
+    #[test]
+    fn test_solver() {
+        assert_eq!(Sums::solve_part1("1,-5,2"), -2);
+        assert_eq!(Sums::solve_part2("1,-5,2"), "1+-5+2");
+        check_examples::<Sums>();
+    }
+
+    #[test]
+    fn test_registration() {
+        let registration = solvers()
+            .into_iter()
+            .find(|s| s.name == "Sums")
+            .expect("Sums registered itself");
+        assert_eq!((registration.year, registration.day), (1, 1));
+        let parsed = (registration.parse)("3,4");
+        assert_eq!((registration.part1)(&parsed), "7");
+        assert_eq!((registration.part2)(&parsed), "3+4");
+        assert_eq!(registration.solve(1, &parsed), "7");
+        assert_eq!(registration.examples.len(), 2);
+        assert_eq!(registration.run_example(&registration.examples[1]), "-1+1");
+        // The intcode days live in the library, so they're always there
+        assert!(solvers().iter().any(|s| s.year == 2019 && s.day == 5));
+    }
+}
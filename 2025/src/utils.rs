@@ -72,17 +72,16 @@ pub struct Grid {
 }
 
 impl Grid {
-    pub fn new(source: &Vec<Vec<char>>) -> Self {
+    pub fn new(source: &[Vec<char>]) -> Self {
         // We're going to initialise from the collection because we want to know the length up front
-        let grid = Grid {
-            grid: source.clone(), // We're making a copy here, this might make more sense to be a borrow
+        Grid {
+            grid: source.to_vec(), // We're making a copy here, this might make more sense to be a borrow
             height: source.len(),
             width: source[0].len(),
-        };
-        return grid;
+        }
     }
 
-    pub fn get(self: &Self, c: Coordinate) -> char {
+    pub fn get(&self, c: Coordinate) -> char {
         if c.x >= i32::try_from(self.width).expect("Width is way too high")
             || c.x < 0
             || c.y >= i32::try_from(self.height).expect("Height is way too high")
@@ -121,7 +120,7 @@ mod tests {
 
     #[test]
     fn test_grid() {
-        let test_grid = Grid::new(&vec![vec!['a', 'b', 'c'], vec!['d', 'e', 'f']]);
+        let test_grid = Grid::new(&[vec!['a', 'b', 'c'], vec!['d', 'e', 'f']]);
         assert_eq!(test_grid.get(Coordinate::new(0, 0)), 'a');
         assert_eq!(test_grid.get(Coordinate::new(1, 0)), 'b');
         assert_eq!(test_grid.get(Coordinate::new(0, 1)), 'd');